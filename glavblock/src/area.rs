@@ -5,9 +5,13 @@ use std::ops::*;
 use std::iter::FromIterator;
 
 use legion::*;
+use serde::{Deserialize, Serialize};
+
+use crate::core::SamosborError;
+use crate::production::StationaryStatus;
 
 /// Виды помещений
-#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum AreaType {
     Living, // жилячейки
     Science, // лаборатории
@@ -18,7 +22,7 @@ pub enum AreaType {
 
 
 /// Вместимость помещения(единицы площади)
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct AreaCapacity(pub usize);
 
 impl From<AreaCapacity> for usize {
@@ -28,7 +32,7 @@ impl From<AreaCapacity> for usize {
 }
 
 /// Занятая площадь(единицы площади)
-#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, )]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct AreaOccupied(pub usize);
 
 impl From<AreaOccupied> for usize {
@@ -57,20 +61,19 @@ impl From<AreaCapacity> for AreaOccupied {
 }
 
 /// Метка того, к какой комнате принадлежит эта штука
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct BelongsToRoom (pub Entity);
 
 /// Узнать сколько в комнате осталось места
 pub fn get_room_free_space(
     world: &mut World,
     room: Entity,
-) -> i32 { // может быть негативным
-    let AreaCapacity (capacity) = world
+) -> Result<i32, SamosborError> { // может быть негативным
+    let AreaCapacity (capacity) = *world
         .entry(room)
-        .unwrap()
+        .ok_or(SamosborError::NoSuchEntity(room))?
         .into_component::<AreaCapacity>()
-        .unwrap()
-        .clone();
+        .map_err(|_| SamosborError::NotARoom(room))?;
     let mut query = <(
         &BelongsToRoom,
         &AreaOccupied
@@ -81,13 +84,28 @@ pub fn get_room_free_space(
             |(&BelongsToRoom (entity), _)|
             entity == room
         ).map(|tup|tup.1) {
-            let occupied_:usize = occupied.clone().into();
+            let occupied_:usize = (*occupied).into();
             sum += occupied_;
         };
-    capacity as i32 - sum as i32
+    Ok(capacity as i32 - sum as i32)
+}
+
+/// Достроена ли герма помещения.
+/// Недостроенным помещением пользоваться нельзя.
+pub fn room_is_ready(
+    world: &World,
+    room: Entity,
+) -> bool {
+    match world.entry_ref(room) {
+        Ok(entry) => entry
+            .get_component::<StationaryStatus>()
+            .map(|status| *status == StationaryStatus::Ready)
+            .unwrap_or(false),
+        Err(_) => false,
+    }
 }
 
-/// Есть ли у нас комната этого назначения
+/// Есть ли у нас готовая комната этого назначения
 /// в которую вместится нечто указанного размера
 pub fn get_sufficent_room(
     world: &mut World,
@@ -95,16 +113,23 @@ pub fn get_sufficent_room(
     type_: AreaType,
 ) -> Option<Entity> {
     let mut areas: HashMap<Entity, (AreaCapacity, AreaOccupied)> = HashMap::new();
+    // Порядок помещений из запроса, чтобы при равенстве
+    // выбор не зависел от порядка в HashMap
+    let mut rooms: Vec<Entity> = Vec::new();
     let mut areasq = <(
         &AreaType,
         &AreaCapacity,
-        &Entity,
+        &StationaryStatus,
+        Entity,
     )>::query();
-    for (_, capacity, entity) in areasq
+    for (_, capacity, _, entity) in areasq
         .iter(world)
-        .filter(|(artype,_, _)| **artype == type_)
+        .filter(|(artype, _, status, _)|
+                **artype == type_
+                && **status == StationaryStatus::Ready)
     {
         areas.insert(*entity, (*capacity, AreaOccupied(0)));
+        rooms.push(*entity);
     }
 
     let mut buildingsq = <(
@@ -114,26 +139,24 @@ pub fn get_sufficent_room(
 
     // Собираем заполненность помещений
     for (room, building_size) in buildingsq.iter(world) {
-        match areas.get_mut(&room.0) {
-            Some((_, occupied)) => *occupied += *building_size,
-            None => (),
+        if let Some((_, occupied)) = areas.get_mut(&room.0) {
+            *occupied += *building_size;
         }
     }
 
     // FIXME: сравнение capacity >= occupied это обход переполнения
     let mut areas_free_space = Vec::from_iter(
-        areas
+        rooms
             .iter()
+            .map(|k| (k, areas[k]))
             .filter(|(_, (c, o))| AreaOccupied::from(*c) >= *o)
-            .map(|(k, (c, o))| (k, AreaOccupied::from(*c) - *o))
+            .map(|(k, (c, o))| (k, AreaOccupied::from(c) - o))
             .filter (|(_, o)| *o >= for_)
     );
 
     // берем наиболее забитые помещения
-    // но в которые тем не менее вместится то что нам надо
-    areas_free_space.sort_by (|(_, o1), (_, o2)| o1.cmp(o2));
-    match areas_free_space.pop () {
-        Some((e, _)) => Some (*e),
-        None => None,
-    }
+    // но в которые тем не менее вместится то что нам надо.
+    // Сортировка устойчивая: из равных берется последнее по запросу.
+    areas_free_space.sort_by_key(|(_, occupied)| *occupied);
+    areas_free_space.pop ().map(|(e, _)| *e)
 }
@@ -0,0 +1,62 @@
+use std::env;
+use std::path::Path;
+use std::process;
+use std::str::FromStr;
+
+use legion::*;
+
+use glavblock::{
+    init_colony,
+    init_resources,
+    load_definitions_or_builtin,
+    simulate,
+    DEFINITIONS_FILE,
+};
+
+/// Сколько ходов гонять, если количество не указано
+const DEFAULT_TURNS: usize = 100;
+
+/// Сид по умолчанию. Одинаковый, чтобы прогоны CI были сравнимы.
+const DEFAULT_SEED: u64 = 0;
+
+/// Подсказать как запускать и выйти с ошибкой
+fn usage() -> ! {
+    eprintln!("Использование: glavblock-headless [количество ходов] [сид]");
+    process::exit(2);
+}
+
+/// Разобрать аргумент. Не указан - берем значение по умолчанию,
+/// указан криво - подсказка и выход.
+fn parse_arg<T: FromStr>(arg: Option<String>, default: T) -> T {
+    match arg {
+        Some(arg) => arg.parse().unwrap_or_else(|_| usage()),
+        None => default,
+    }
+}
+
+/// Прогон экономики без окна:
+/// `glavblock-headless [количество ходов] [сид]`
+fn main() {
+    let mut args = env::args().skip(1);
+    let turns = parse_arg(args.next(), DEFAULT_TURNS);
+    let seed = parse_arg(args.next(), DEFAULT_SEED);
+    if args.next().is_some() {
+        usage();
+    }
+    if let Err(e) = load_definitions_or_builtin(Path::new(DEFINITIONS_FILE)) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+    let mut world = World::default();
+    let mut resources = init_resources(seed);
+    if let Err(e) = init_colony(&mut world, &mut resources) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+    simulate(
+        &mut world,
+        &mut resources,
+        turns,
+        |report| println!("{}", report),
+    );
+}
@@ -0,0 +1,209 @@
+use legion::*;
+
+use crate::core::*;
+use crate::production::*;
+use crate::resources::*;
+use crate::resources::Resource::ConcentratT1;
+use crate::storage::*;
+use crate::people::*;
+use crate::area::*;
+use crate::samosbor::SamosborClock;
+use crate::military::*;
+use crate::nutrition::RationPolicy;
+use crate::utilities::connect_room;
+
+/// На сколько ходов стартовой колонии хватает концентрата
+pub const START_FOOD_TURNS: usize = 100;
+
+/// Глобальные ресурсы legion, без которых ход не крутится
+pub fn init_resources(seed: u64) -> Resources {
+    let mut resources = Resources::default();
+    resources.insert(Dice::new(seed));
+    resources.insert(SamosborClock::new());
+    resources.insert(RationPolicy::new());
+    resources
+}
+
+/// Стартовая колония
+pub fn init_colony(
+    world: &mut World,
+    resources: &mut Resources,
+) -> Result<(), SamosborError> {
+    let mut dice = resources
+        .get_mut::<Dice>()
+        .unwrap();
+
+    // казарма с рассчетом №1-Ж
+    let barracks = spawn_ready_germ(
+        world,
+        Tier::T2,
+        AreaType::Military,
+    )?;
+    spawn_1_g(world, barracks)?;
+
+    // Наряд ОБЧУ на случай беспорядков. В казарме 1-Ж ему
+    // места нет, живет в своей.
+    let obcu_barracks = spawn_ready_germ(
+        world,
+        Tier::T2,
+        AreaType::Military,
+    )?;
+    let obcu = form_squad(world, MilitaryDep::OBCU, obcu_barracks)?;
+    for role in [SquadRole::Granit, SquadRole::Granit, SquadRole::Rake, SquadRole::Rake].iter() {
+        let comrad = spawn_comrad(
+            world,
+            Profession::Likvidator,
+            Tier::T1,
+            MilitaryDep::OBCU,
+            SciSpec::None,
+            obcu_barracks,
+        )?;
+        reinforce_squad(world, obcu, comrad, *role)?;
+        // Выходит сразу снаряженным, как и 1-Ж
+        if let Some(mut entry) = world.entry(comrad) {
+            entry.add_component(Equipped());
+        }
+    }
+
+    // T2 производственное помещение под установку верстака, станка, печи, и чанов.
+    // Здесь же генератор и насосы на всю колонию.
+    let manufactory = spawn_ready_germ(
+        world,
+        Tier::T2,
+        AreaType::Industrial,
+    )?;
+    let generator = spawn_ready_stationary(world, Stationary::Generator, manufactory);
+    let water_pump = spawn_ready_stationary(world, Stationary::WaterPump, manufactory);
+    let air_pump = spawn_ready_stationary(world, Stationary::AirPump, manufactory);
+    for node in [generator, water_pump, air_pump].iter() {
+        let _ = connect_room(world, manufactory, *node);
+    }
+
+    // T2 Склад с чанами и стеллажами
+    let stock = spawn_ready_germ(
+        world,
+        Tier::T2,
+        AreaType::Party,
+    )?;
+    for _ in 0..12 {
+        spawn_ready_stationary(world, Stationary::Rack, stock);
+    }
+    for _ in 0..2 {
+        spawn_ready_stationary(world, Stationary::Barrel, stock);
+    }
+    let _ = connect_room(world, stock, water_pump);
+
+    // Т1 комнатка для исследований
+    let lab = spawn_ready_germ(
+        world,
+        Tier::T1,
+        AreaType::Science,
+    )?;
+    let _ = connect_room(world, lab, generator);
+
+    let start_sci_spec = random_sci_spec(&mut dice);
+    let cell_sciencists = spawn_ready_germ(
+        world,
+        Tier::T1,
+        AreaType::Living,
+    )?;
+    spawn_comrad(
+        world,
+        Profession::Scientist,
+        Tier::T1,
+        MilitaryDep::None,
+        start_sci_spec,
+        cell_sciencists,
+    )?;
+
+    // Мусорщики. Ходят во внешний блок за сырьем.
+    let cell_stalkers = spawn_ready_germ(
+        world,
+        Tier::T1,
+        AreaType::Living,
+    )?;
+    for _ in 0..3 {
+        spawn_comrad(
+            world,
+            Profession::Stalker,
+            Tier::T1,
+            MilitaryDep::None,
+            SciSpec::None,
+            cell_stalkers,
+        )?;
+    }
+
+    // Жилячейки
+    for _ in 0..33 {
+        let cell = spawn_ready_germ(
+            world,
+            Tier::T1,
+            AreaType::Living,
+        )?;
+        for _ in 0..3 {
+            spawn_comrad(
+                world,
+                Profession::Worker,
+                Tier::T1,
+                MilitaryDep::None,
+                SciSpec::None,
+                cell,
+            )?;
+        }
+    };
+
+    // Ресурсы. Стартовые запасы рассчитаны под стартовый склад,
+    // но определения могут его уменьшить. Что не влезло - пропало.
+
+    // Концентрата на всех, пока своя кухня не заработала
+    let colonists: usize = people_by_profession(world).values().sum();
+    let _ = put_resource(
+        world,
+        ConcentratT1,
+        RealUnits(colonists * START_FOOD_TURNS),
+    );
+    let _ = put_resource(
+        world,
+        Resource::ScrapT1,
+        RealUnits(500),
+    );
+    let _ = put_resource(
+        world,
+        Resource::ScrapT2,
+        RealUnits(50),
+    );
+
+    let _ = put_resource(
+        world,
+        Resource::PolymerT1,
+        RealUnits(100),
+    );
+    let _ = put_resource(
+        world,
+        Resource::PolymerT2,
+        RealUnits(10),
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fresh_colony_not_overcrowded() {
+        let mut world = World::default();
+        let mut resources = init_resources(0);
+        init_colony(&mut world, &mut resources).unwrap();
+        let mut rooms_q = <(Entity, &AreaType)>::query();
+        let dwellings: Vec<Entity> = rooms_q
+            .iter(&world)
+            .filter(|(_, area)| **area == AreaType::Living || **area == AreaType::Military)
+            .map(|(e, _)| *e)
+            .collect();
+        assert!(!dwellings.is_empty());
+        for room in dwellings.iter() {
+            assert!(get_room_free_space(&mut world, *room).unwrap() >= 0);
+        }
+    }
+}
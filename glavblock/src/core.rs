@@ -1,29 +1,274 @@
+use std::fmt;
+use std::error::Error;
 use std::hash::Hash;
-use rand::thread_rng;
+use legion::Entity;
 use rand::Rng;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
 
-/// Бросить кубы
-pub fn d(rolls:u8, sides:u8) -> usize {
+use crate::area::*;
+use crate::production::*;
+use crate::resources::*;
+use crate::storage::*;
+use crate::science::Tech;
+use crate::people::{Profession, MilitaryDep};
+
+/// Кости колонии. Единственный источник случайности симуляции.
+/// Лежит в `Resources`, сохраняется вместе с колонией,
+/// так что один и тот же сид и одни и те же приказы
+/// дают одну и ту же партию.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Dice(ChaCha8Rng);
+
+impl Dice {
+    pub fn new(seed: u64) -> Dice {
+        Dice(ChaCha8Rng::seed_from_u64(seed))
+    }
+}
+
+/// Бросить кубы. На каждом кубе выпадает от 1 до `sides`.
+pub fn d(dice: &mut Dice, rolls:u8, sides:u8) -> usize {
     if sides < 1 || rolls < 1 {
         0
     } else {
-        let mut rng = thread_rng();
         let mut result = 0;
         for _ in 0..rolls {
-            result += rng.gen_range(0..sides) as usize
+            result += dice.0.gen_range(1..=sides) as usize
         }
         result
     }
 }
 
+/// Почему приказ не выполнен
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum SamosborError {
-    NoEmptyArea,
-    NotEnoughArea,
-    NotEnoughResources,
+    /// Нет готового помещения этого назначения, куда влезет нужное
+    NoEmptyArea {
+        area_type: AreaType,
+        required: AreaOccupied,
+    },
+    /// В помещении не хватает места
+    NotEnoughArea {
+        room: Entity,
+        required: AreaOccupied,
+        free: i32,
+    },
+    /// Помещение не того назначения
+    WrongAreaType {
+        room: Entity,
+        expected: AreaType,
+        actual: AreaType,
+    },
+    /// Такой сущности нет (умер, снесли, не было)
+    NoSuchEntity(Entity),
+    /// Сущность есть, но это не помещение
+    NotARoom(Entity),
+    /// Герма помещения еще не достроена
+    RoomNotReady(Entity),
+    /// Не хватает ресурса
+    NotEnoughResources {
+        resource: Resource,
+        required: RealUnits,
+        available: RealUnits,
+    },
+    /// На складах не хватило места, `rest` ресурса не поместилось
+    StorageOverflow {
+        resource: Resource,
+        rest: RealUnits,
+    },
+    /// Не хватает свободных людей нужной профессии
+    NotEnoughPeople {
+        profession: Profession,
+        required: usize,
+        available: usize,
+    },
+    /// Сущность есть, но это не отряд
+    NotASquad(Entity),
+    /// Комрад не ликвидатор нужного отдела
+    NotASoldier(Entity),
+    /// Такого военного отдела нет
+    NotADepartment(MilitaryDep),
+    /// Боец уже в отряде
+    AlreadyInSquad(Entity),
+    /// Нет введенного в эксплуатацию оборудования
+    NoStationary(Stationary),
+    /// С задачей в таком состоянии так нельзя
+    InvalidTaskState(Entity),
+    /// Герм такого уровня не бывает
+    InvalidTier(Tier),
+    /// Технология еще не изучена
+    NotResearched(Tech),
+    /// Технология уже изучена
+    AlreadyResearched(Tech),
+    /// Технология уже исследуется
+    AlreadyResearching(Tech),
+    /// Вылазка без единого хода
+    InvalidExpeditionLength(usize),
+    /// В помещении еще стоит оборудование, лежат ящики или квартирует отряд
+    RoomNotEmpty(Entity),
+    /// Стационарка целая, чинить нечего
+    NoRepairNeeded(Entity),
+    /// Стационарка не дает ни электричества, ни воды, ни воздуха
+    NotAUtilityNode(Entity),
+    /// К узлу уже подключено сколько он тянет
+    NodeOverloaded(Entity),
+    /// Стационарка сейчас не в том состоянии: строится, чинится или уже разбирается
+    StationaryBusy(Entity),
+    /// Сохранение не записалось или не читается
+    Save(String),
+    /// Файл определений не читается или не прошел проверку
+    Definitions(String),
 }
 
+impl fmt::Display for SamosborError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SamosborError::NoEmptyArea { area_type, required } => write!(
+                f,
+                "Нет готового помещения {:?} со свободной площадью {}",
+                area_type,
+                required.0,
+            ),
+            SamosborError::NotEnoughArea { room, required, free } => write!(
+                f,
+                "В помещении {:?} не хватает места: нужно {}, свободно {}",
+                room,
+                required.0,
+                free,
+            ),
+            SamosborError::WrongAreaType { room, expected, actual } => write!(
+                f,
+                "Помещение {:?} - {:?}, а нужно {:?}",
+                room,
+                actual,
+                expected,
+            ),
+            SamosborError::NoSuchEntity(entity) => write!(
+                f,
+                "{:?} не существует",
+                entity,
+            ),
+            SamosborError::NotARoom(entity) => write!(
+                f,
+                "{:?} не помещение",
+                entity,
+            ),
+            SamosborError::RoomNotReady(room) => write!(
+                f,
+                "Герма помещения {:?} еще строится",
+                room,
+            ),
+            SamosborError::NotEnoughResources { resource, required, available } => write!(
+                f,
+                "Не хватает {:?}: нужно {}, есть {}",
+                resource,
+                required.0,
+                available.0,
+            ),
+            SamosborError::StorageOverflow { resource, rest } => write!(
+                f,
+                "Склады переполнены: не поместилось {} {:?}",
+                rest.0,
+                resource,
+            ),
+            SamosborError::NotEnoughPeople { profession, required, available } => write!(
+                f,
+                "Не хватает свободных {:?}: нужно {}, есть {}",
+                profession,
+                required,
+                available,
+            ),
+            SamosborError::NotASquad(entity) => write!(
+                f,
+                "{:?} не отряд",
+                entity,
+            ),
+            SamosborError::NotASoldier(comrad) => write!(
+                f,
+                "{:?} не ликвидатор этого отдела",
+                comrad,
+            ),
+            SamosborError::NotADepartment(dep) => write!(
+                f,
+                "{:?} не военный отдел",
+                dep,
+            ),
+            SamosborError::AlreadyInSquad(comrad) => write!(
+                f,
+                "{:?} уже в отряде",
+                comrad,
+            ),
+            SamosborError::NoStationary(stationary) => write!(
+                f,
+                "Нет готового оборудования {:?}",
+                stationary,
+            ),
+            SamosborError::InvalidTaskState(task) => write!(
+                f,
+                "Задача {:?} в неподходящем состоянии",
+                task,
+            ),
+            SamosborError::InvalidTier(tier) => write!(
+                f,
+                "Гермы уровня {:?} не бывает",
+                tier,
+            ),
+            SamosborError::NotResearched(tech) => write!(
+                f,
+                "Технология {:?} не изучена",
+                tech,
+            ),
+            SamosborError::AlreadyResearched(tech) => write!(
+                f,
+                "Технология {:?} уже изучена",
+                tech,
+            ),
+            SamosborError::AlreadyResearching(tech) => write!(
+                f,
+                "Технология {:?} уже исследуется",
+                tech,
+            ),
+            SamosborError::InvalidExpeditionLength(length) => write!(
+                f,
+                "Вылазки на {} ходов не бывает",
+                length,
+            ),
+            SamosborError::RoomNotEmpty(room) => write!(
+                f,
+                "В помещении {:?} еще стоит оборудование, лежат ящики или квартирует отряд",
+                room,
+            ),
+            SamosborError::NoRepairNeeded(stationary) => write!(
+                f,
+                "{:?} не нуждается в ремонте",
+                stationary,
+            ),
+            SamosborError::NotAUtilityNode(node) => write!(
+                f,
+                "{:?} не узел электричества, воды или вентиляции",
+                node,
+            ),
+            SamosborError::NodeOverloaded(node) => write!(
+                f,
+                "Узел {:?} перегружен, больше помещений не потянет",
+                node,
+            ),
+            SamosborError::StationaryBusy(stationary) => write!(
+                f,
+                "{:?} сейчас строится, ремонтируется или разбирается",
+                stationary,
+            ),
+            SamosborError::Save(msg) => write!(f, "Сохранение: {}", msg),
+            SamosborError::Definitions(msg) => write!(f, "Определения: {}", msg),
+        }
+    }
+}
+
+impl Error for SamosborError {}
+
 /// Уровень(изделия, опыта, ресурса и тп)
-#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash, Serialize, Deserialize)]
 pub enum Tier {
     NoTier, // уникальные штуки
     T1,
@@ -35,3 +280,34 @@ pub enum _Language {
     RU,
     EN,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dice_roll_from_one() {
+        let mut dice = Dice::new(0);
+        for _ in 0..1000 {
+            assert!((1..=6).contains(&d(&mut dice, 1, 6)));
+            assert!((3..=18).contains(&d(&mut dice, 3, 6)));
+        }
+    }
+
+    #[test]
+    fn test_same_seed_same_rolls() {
+        let mut first = Dice::new(42);
+        let mut second = Dice::new(42);
+        let first_rolls: Vec<usize> = (0..100).map(|_| d(&mut first, 3, 6)).collect();
+        let second_rolls: Vec<usize> = (0..100).map(|_| d(&mut second, 3, 6)).collect();
+        assert_eq!(first_rolls, second_rolls);
+        assert!(first_rolls.iter().all(|roll| (3..=18).contains(roll)));
+    }
+
+    #[test]
+    fn test_zero_dice_roll_nothing() {
+        let mut dice = Dice::new(0);
+        assert_eq!(d(&mut dice, 0, 6), 0);
+        assert_eq!(d(&mut dice, 2, 0), 0);
+    }
+}
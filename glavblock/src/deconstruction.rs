@@ -0,0 +1,314 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use legion::*;
+use serde::{Deserialize, Serialize};
+
+use crate::core::*;
+use crate::area::*;
+use crate::people::*;
+use crate::production::*;
+use crate::resources::*;
+use crate::storage::*;
+use crate::definitions::definitions;
+use crate::military::{Squad, DispatchedTo};
+use crate::utilities::disconnect_node;
+
+/// Стационарку разбирают, чтобы собрать в другом помещении
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelocateTo (pub Entity);
+
+/// Что вернется на склад при разборке стационарки
+pub fn deconstruction_refund(
+    stationary: Stationary,
+) -> HashMap<Resource, RealUnits> {
+    let percent = definitions().refund_percent;
+    stationary_required_resources(stationary)
+        .iter()
+        .map(|(res, amount)| (*res, RealUnits(amount.0 * percent / 100)))
+        .filter(|(_, amount)| amount.0 > 0)
+        .collect()
+}
+
+/// Разбирать вдвое быстрее чем строить
+fn dismantling_requirements(
+    requirements: Vec<TaskMeta>,
+) -> Vec<TaskMeta> {
+    requirements
+        .iter()
+        .map(|task| TaskMeta {
+            bp: BuildPower(std::cmp::max(task.bp.0 / 2, 1)),
+            stationary: Stationary::None,
+            sci_spec: SciSpec::None,
+            ..*task
+        })
+        .collect()
+}
+
+/// Поставить задачи на разборку
+fn push_dismantling_tasks(
+    world: &mut World,
+    target: Entity,
+    status: StationaryStatus,
+    requirements: Vec<TaskMeta>,
+    priority: TaskPriority,
+) {
+    if let Some(mut entry) = world.entry(target) {
+        entry.add_component(ResumeStatus(status));
+        entry.add_component(StationaryStatus::Dismantling);
+    }
+    for task_meta in dismantling_requirements(requirements).iter() {
+        push_task(world, BelongsToStationary(target), *task_meta, priority);
+    }
+}
+
+/// Стационарка, которую можно разбирать: готовая или сломанная
+fn stationary_for_dismantling(
+    world: &World,
+    stationary: Entity,
+) -> Result<(Stationary, Entity, StationaryStatus), SamosborError> {
+    let entry = world
+        .entry_ref(stationary)
+        .map_err(|_| SamosborError::NoSuchEntity(stationary))?;
+    let kind = *entry
+        .get_component::<Stationary>()
+        .map_err(|_| SamosborError::NoSuchEntity(stationary))?;
+    let status = *entry
+        .get_component::<StationaryStatus>()
+        .map_err(|_| SamosborError::NoSuchEntity(stationary))?;
+    let BelongsToRoom(room) = *entry
+        .get_component::<BelongsToRoom>()
+        .map_err(|_| SamosborError::NoSuchEntity(stationary))?;
+    if status != StationaryStatus::Ready && status != StationaryStatus::Broken {
+        return Err(SamosborError::StationaryBusy(stationary))
+    }
+    Ok((kind, room, status))
+}
+
+/// Разобрать стационарку. Разборка - работа как постройка,
+/// только вдвое короче. По окончании часть ресурсов
+/// возвращается на склад, площадь освобождается.
+pub fn start_deconstruction(
+    world: &mut World,
+    stationary: Entity,
+    priority: TaskPriority,
+) -> Result<(), SamosborError> {
+    let (kind, _, status) = stationary_for_dismantling(world, stationary)?;
+    push_dismantling_tasks(world, stationary, status, stationary_requirements(kind), priority);
+    Ok(())
+}
+
+/// Перенести стационарку в другое помещение того же назначения.
+/// Ресурсы не тратятся, только работа. Место в новом помещении
+/// проверяется сейчас и еще раз, когда стационарку перенесут.
+pub fn start_relocation(
+    world: &mut World,
+    stationary: Entity,
+    target: Entity,
+    priority: TaskPriority,
+) -> Result<(), SamosborError> {
+    let (kind, room, status) = stationary_for_dismantling(world, stationary)?;
+    let free = get_room_free_space(world, target)?;
+    let area_of = |room: Entity| -> Result<AreaType, SamosborError> {
+        world
+            .entry_ref(room)
+            .map_err(|_| SamosborError::NoSuchEntity(room))?
+            .get_component::<AreaType>()
+            .copied()
+            .map_err(|_| SamosborError::NotARoom(room))
+    };
+    let expected = area_of(room)?;
+    let actual = area_of(target)?;
+    if expected != actual {
+        return Err(SamosborError::WrongAreaType {
+            room: target,
+            expected,
+            actual,
+        })
+    }
+    if !room_is_ready(world, target) {
+        return Err(SamosborError::RoomNotReady(target))
+    }
+    let required = stationary_size(kind);
+    if free < required.0 as i32 {
+        return Err(SamosborError::NotEnoughArea {
+            room: target,
+            required,
+            free,
+        })
+    }
+    if let Some(mut entry) = world.entry(stationary) {
+        entry.add_component(RelocateTo(target));
+    }
+    push_dismantling_tasks(world, stationary, status, stationary_requirements(kind), priority);
+    Ok(())
+}
+
+/// Снести герму. В помещении не должно остаться ни оборудования,
+/// ни ящиков со склада, ни отрядов. Жильцов переселит жилищный вопрос.
+pub fn start_germ_deconstruction(
+    world: &mut World,
+    room: Entity,
+    priority: TaskPriority,
+) -> Result<(), SamosborError> {
+    get_room_free_space(world, room)?;
+    let tier = {
+        let entry = world
+            .entry_ref(room)
+            .map_err(|_| SamosborError::NoSuchEntity(room))?;
+        let status = *entry
+            .get_component::<StationaryStatus>()
+            .map_err(|_| SamosborError::NotARoom(room))?;
+        if status != StationaryStatus::Ready {
+            return Err(SamosborError::StationaryBusy(room))
+        }
+        *entry
+            .get_component::<Tier>()
+            .map_err(|_| SamosborError::NotARoom(room))?
+    };
+    let mut inside_q = <&BelongsToRoom>::query()
+        .filter(
+            component::<Stationary>()
+            | component::<VolumeOccupied>()
+            | component::<Squad>()
+        );
+    if inside_q.iter(world).any(|BelongsToRoom(r)| *r == room) {
+        return Err(SamosborError::RoomNotEmpty(room))
+    }
+    push_dismantling_tasks(world, room, StationaryStatus::Ready, germ_requirements(tier), priority);
+    Ok(())
+}
+
+/// Перенести стационарку с местами хранения в новое помещение.
+/// Если место там уже заняли - стационарка остается где была.
+/// Перенесенный узел коммуникаций подключают заново.
+fn relocate(
+    world: &mut World,
+    stationary: Entity,
+    target: Entity,
+) {
+    let (size, status) = match world.entry_ref(stationary) {
+        Ok(entry) => match entry.get_component::<AreaOccupied>() {
+            Ok(size) => (
+                *size,
+                entry
+                    .get_component::<ResumeStatus>()
+                    .map(|ResumeStatus(status)| *status)
+                    .unwrap_or(StationaryStatus::Ready),
+            ),
+            Err(_) => return,
+        },
+        Err(_) => return,
+    };
+    let fits = get_room_free_space(world, target)
+        .map(|free| free >= size.0 as i32)
+        .unwrap_or(false);
+    if fits {
+        disconnect_node(world, stationary);
+        let mut containers_q = <(&BelongsToStationary, &mut BelongsToRoom)>::query()
+            .filter(component::<VolumeOccupied>());
+        for (BelongsToStationary(s), room) in containers_q.iter_mut(world) {
+            if *s == stationary {
+                *room = BelongsToRoom(target);
+            }
+        }
+        if let Some(mut entry) = world.entry(stationary) {
+            entry.add_component(BelongsToRoom(target));
+        }
+    }
+    if let Some(mut entry) = world.entry(stationary) {
+        entry.remove_component::<RelocateTo>();
+        entry.remove_component::<ResumeStatus>();
+        entry.add_component(status);
+    }
+}
+
+/// Разборка без открытых задач закончена:
+/// стационарку переносят или убирают с возвратом ресурсов,
+/// снесенная герма исчезает вместе с помещением.
+pub(crate) fn complete_deconstructions(
+    world: &mut World,
+    _resources: &mut Resources,
+) {
+    let mut open_q = <&BelongsToStationary>::query()
+        .filter(component::<TaskMeta>());
+    let open: HashSet<Entity> = open_q
+        .iter(world)
+        .map(|BelongsToStationary(e)| *e)
+        .collect();
+    let mut done_q = <(Entity, &StationaryStatus, Option<&Stationary>, Option<&RelocateTo>)>::query();
+    let done: Vec<(Entity, Option<Stationary>, Option<Entity>)> = done_q
+        .iter(world)
+        .filter(|(e, status, _, _)|
+                **status == StationaryStatus::Dismantling
+                && !open.contains(*e))
+        .map(|(e, _, stationary, relocate)| (
+            *e,
+            stationary.cloned(),
+            relocate.map(|RelocateTo(room)| *room),
+        ))
+        .collect();
+    for (entity, stationary, relocate_to) in done.iter() {
+        match (stationary, relocate_to) {
+            (Some(_), Some(target)) => relocate(world, *entity, *target),
+            (Some(kind), None) => {
+                let _ = dismantle_stationary(world, *entity);
+                for (res, amount) in deconstruction_refund(*kind).iter() {
+                    // Что не влезло на склады - бросили у гермы
+                    put_or_dump(world, *res, *amount);
+                }
+            },
+            (None, _) => {
+                // Отряды, стоявшие в снесенном помещении, возвращаются
+                let mut squads_q = <(Entity, &DispatchedTo)>::query();
+                let recalled: Vec<Entity> = squads_q
+                    .iter(world)
+                    .filter(|(_, DispatchedTo(room))| room == entity)
+                    .map(|(squad, _)| *squad)
+                    .collect();
+                for squad in recalled.iter() {
+                    if let Some(mut entry) = world.entry(*squad) {
+                        entry.remove_component::<DispatchedTo>();
+                    }
+                }
+                world.remove(*entity);
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::turn::clean_up_completed_tasks;
+
+    #[test]
+    fn test_stock_with_crates_stays() {
+        let mut world = World::default();
+        let stock = spawn_ready_germ(&mut world, Tier::T1, AreaType::Party).unwrap();
+        put_resource(&mut world, Resource::Concrete, RealUnits(1)).unwrap();
+        assert_eq!(
+            start_germ_deconstruction(&mut world, stock, TaskPriority(0)),
+            Err(SamosborError::RoomNotEmpty(stock)),
+        );
+    }
+
+    #[test]
+    fn test_refund_kept_without_storage() {
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        let room = spawn_ready_germ(&mut world, Tier::T2, AreaType::Industrial).unwrap();
+        let generator = spawn_ready_stationary(&mut world, Stationary::Generator, room);
+        start_deconstruction(&mut world, generator, TaskPriority(0)).unwrap();
+        let mut tasks_q = <&mut TaskMeta>::query();
+        for task in tasks_q.iter_mut(&mut world) {
+            task.bp = BuildPower(0);
+        }
+        clean_up_completed_tasks(&mut world, &mut resources);
+        complete_deconstructions(&mut world, &mut resources);
+        assert!(world.entry_ref(generator).is_err());
+        for (res, amount) in deconstruction_refund(Stationary::Generator).iter() {
+            assert_eq!(how_much_we_have(&mut world, *res), *amount);
+        }
+    }
+}
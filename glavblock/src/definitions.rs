@@ -0,0 +1,277 @@
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::*;
+use crate::area::*;
+use crate::people::*;
+use crate::production::*;
+use crate::resources::*;
+use crate::storage::*;
+
+/// Файл определений, который ищется при запуске
+pub const DEFINITIONS_FILE: &str = "definitions.ron";
+
+/// Параметры стационарки
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StationaryDef {
+    pub size: AreaOccupied,
+    pub build_power: BuildPower,
+    pub required_resources: HashMap<Resource, RealUnits>,
+    pub requirements: Vec<TaskMeta>,
+}
+
+/// Параметры гермы конкретного тира
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GermDef {
+    pub capacity: AreaCapacity,
+    pub requirements: Vec<TaskMeta>,
+}
+
+/// Параметры хранения ресурса
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResourceDef {
+    pub piece_size: VolumeOccupied,
+    pub storage: StorageType,
+}
+
+/// Игровые определения: все что дизайнер может
+/// перебалансировать без перекомпиляции.
+/// После валидации гарантированно содержит все стационарки,
+/// все тиры герм и все ресурсы.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Definitions {
+    #[serde(default)]
+    pub stationaries: HashMap<Stationary, StationaryDef>,
+    #[serde(default)]
+    pub germs: HashMap<Tier, GermDef>,
+    #[serde(default)]
+    pub resources: HashMap<Resource, ResourceDef>,
+    /// Сколько процентов ресурсов стационарки возвращается при разборке
+    #[serde(default = "builtin_refund_percent")]
+    pub refund_percent: usize,
+}
+
+/// Тиры, в которых бывают гермы
+const GERM_TIERS: [Tier; 3] = [Tier::T1, Tier::T2, Tier::T3];
+
+/// При разборке возвращается половина
+fn builtin_refund_percent() -> usize {
+    50
+}
+
+impl Definitions {
+    /// Значения, зашитые в код
+    pub fn builtin() -> Definitions {
+        let stationaries = STATIONARIES
+            .iter()
+            .map(|s| (*s, StationaryDef {
+                size: builtin_stationary_size(*s),
+                build_power: builtin_stationary_build_power(*s),
+                required_resources: builtin_stationary_required_resources(*s),
+                requirements: builtin_stationary_requirements(*s),
+            }))
+            .collect();
+        let germs = GERM_TIERS
+            .iter()
+            .map(|t| (*t, GermDef {
+                capacity: builtin_germ_capacity(*t),
+                requirements: builtin_germ_requirements(*t),
+            }))
+            .collect();
+        let resources = RESOURCES
+            .iter()
+            .map(|r| (*r, ResourceDef {
+                piece_size: builtin_piece_size(*r),
+                storage: builtin_container_type(*r),
+            }))
+            .collect();
+        Definitions {
+            stationaries,
+            germs,
+            resources,
+            refund_percent: builtin_refund_percent(),
+        }
+    }
+
+    /// Поверх встроенных значений накатить то, что есть в `other`.
+    /// Чего в `other` нет - остается встроенным.
+    pub fn overlay(other: Definitions) -> Definitions {
+        let mut result = Definitions::builtin();
+        result.stationaries.extend(other.stationaries);
+        result.germs.extend(other.germs);
+        result.resources.extend(other.resources);
+        result.refund_percent = other.refund_percent;
+        result
+    }
+
+    pub fn stationary(&self, stationary: Stationary) -> &StationaryDef {
+        &self.stationaries[&stationary]
+    }
+
+    pub fn germ(&self, tier: Tier) -> &GermDef {
+        &self.germs[&tier]
+    }
+
+    pub fn resource(&self, resource: Resource) -> &ResourceDef {
+        &self.resources[&resource]
+    }
+
+    /// Проверить что определения полные и ими можно играть
+    pub fn validate(&self) -> Result<(), SamosborError> {
+        self.check().map_err(SamosborError::Definitions)
+    }
+
+    fn check(&self) -> Result<(), String> {
+        for stationary in STATIONARIES.iter() {
+            let def = self.stationaries
+                .get(stationary)
+                .ok_or(format!("{:?}: no definition", stationary))?;
+            if *stationary != Stationary::None && def.size == AreaOccupied(0) {
+                return Err(format!("{:?}: size must be positive", stationary))
+            }
+            for (res, amount) in def.required_resources.iter() {
+                if *amount == RealUnits(0) {
+                    return Err(format!("{:?}: zero amount of {:?}", stationary, res))
+                }
+            }
+            validate_requirements(&format!("{:?}", stationary), &def.requirements)?;
+        }
+        if self.refund_percent > 100 {
+            return Err("Refund can't be over 100 percent".to_string())
+        }
+        if self.germs.contains_key(&Tier::NoTier) {
+            return Err("Germ of NoTier is not allowed".to_string())
+        }
+        for tier in GERM_TIERS.iter() {
+            let def = self.germs
+                .get(tier)
+                .ok_or(format!("Germ {:?}: no definition", tier))?;
+            if def.capacity == AreaCapacity(0) {
+                return Err(format!("Germ {:?}: capacity must be positive", tier))
+            }
+            validate_requirements(&format!("Germ {:?}", tier), &def.requirements)?;
+        }
+        for resource in RESOURCES.iter() {
+            let def = self.resources
+                .get(resource)
+                .ok_or(format!("{:?}: no definition", resource))?;
+            // Ноль ломает пересчет объема в штуки,
+            // больше контейнера - ресурс некуда положить.
+            if def.piece_size == VolumeOccupied(0)
+                || def.piece_size > VolumeOccupied::from(CONTAINER_VOLUME)
+            {
+                return Err(format!(
+                    "{:?}: piece size must be in 1..={}",
+                    resource,
+                    CONTAINER_VOLUME.0,
+                ))
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Рабочие задачи должны быть выполнимы
+fn validate_requirements(
+    owner: &str,
+    requirements: &[TaskMeta],
+) -> Result<(), String> {
+    for task in requirements.iter() {
+        if task.tier == Tier::NoTier {
+            return Err(format!("{}: task of NoTier worker", owner))
+        }
+        if task.prof == Profession::NoProf {
+            return Err(format!("{}: task for NoProf", owner))
+        }
+        if task.bp == BuildPower(0) {
+            return Err(format!("{}: task with zero build power", owner))
+        }
+    }
+    Ok(())
+}
+
+/// Определения, которыми сейчас живет симуляция.
+/// None - пока не загружены, используются встроенные.
+static DEFINITIONS: RwLock<Option<Arc<Definitions>>> = RwLock::new(None);
+
+/// Текущие определения
+pub fn definitions() -> Arc<Definitions> {
+    if let Some(defs) = DEFINITIONS.read().unwrap().as_ref() {
+        return defs.clone()
+    }
+    let mut slot = DEFINITIONS.write().unwrap();
+    slot.get_or_insert_with(|| Arc::new(Definitions::builtin()))
+        .clone()
+}
+
+/// Проверить и установить определения
+pub fn set_definitions(
+    defs: Definitions,
+) -> Result<(), SamosborError> {
+    defs.validate()?;
+    *DEFINITIONS.write().unwrap() = Some(Arc::new(defs));
+    Ok(())
+}
+
+/// Прочитать определения из RON файла.
+/// Все что в файле не указано берется из встроенных значений.
+pub fn load_definitions(
+    path: &Path,
+) -> Result<(), SamosborError> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| SamosborError::Definitions(format!("{}: {}", path.display(), e)))?;
+    let from_file: Definitions = ron::de::from_str(&text)
+        .map_err(|e| SamosborError::Definitions(format!("{}: {}", path.display(), e)))?;
+    set_definitions(Definitions::overlay(from_file))
+}
+
+/// Загрузить определения из файла если он есть,
+/// иначе остаться на встроенных.
+pub fn load_definitions_or_builtin(
+    path: &Path,
+) -> Result<(), SamosborError> {
+    if path.exists() {
+        load_definitions(path)
+    } else {
+        set_definitions(Definitions::builtin())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_is_valid() {
+        assert_eq!(Definitions::builtin().validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_example_file_overlays_builtin() {
+        let from_file: Definitions = ron::de::from_str(
+            include_str!("../definitions.example.ron")
+        ).unwrap();
+        let defs = Definitions::overlay(from_file);
+        assert_eq!(defs.validate(), Ok(()));
+        assert_eq!(defs.stationary(Stationary::FormatFurnace).size, AreaOccupied(50));
+        // Чего в файле нет - осталось встроенным
+        assert_eq!(
+            defs.stationary(Stationary::Rack).size,
+            Definitions::builtin().stationary(Stationary::Rack).size,
+        );
+    }
+
+    #[test]
+    fn test_zero_piece_size_rejected() {
+        let mut defs = Definitions::builtin();
+        defs.resources.insert(Resource::Concrete, ResourceDef {
+            piece_size: VolumeOccupied(0),
+            storage: StorageType::Solid,
+        });
+        assert!(defs.validate().is_err());
+    }
+}
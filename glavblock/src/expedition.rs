@@ -0,0 +1,267 @@
+use std::collections::HashMap;
+
+use legion::*;
+use serde::{Deserialize, Serialize};
+
+use crate::core::*;
+use crate::people::*;
+use crate::production::*;
+use crate::resources::*;
+use crate::storage::*;
+use crate::samosbor::*;
+use crate::nutrition::HUNGER_PER_TURN;
+
+/// Сколько ходов отлеживается раненый
+const INJURY_TURNS: usize = 10;
+
+/// Вылазка мусорщиков во внешний блок
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Expedition {
+    pub turns_left: usize,
+    pub length: usize, // на сколько ходов уходили
+    pub loot: HashMap<Resource, RealUnits>, // что несут с собой
+}
+
+/// Комрад ушел в вылазку.
+/// Дома не ест, не работает и под самосбор в блоке не попадает.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OnExpedition (pub Entity);
+
+/// Что принесли из вылазки и кто не вернулся
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExpeditionReport {
+    pub returned: usize,
+    pub killed: usize,
+    pub injured: usize,
+    pub loot: Vec<(Resource, RealUnits)>,
+    pub dumped: Vec<(Resource, RealUnits)>, // не влезло на склады, брошено у гермы
+}
+
+/// Паек на вылазку: по пайке на мусорщика на ход
+fn expedition_supplies(
+    members: usize,
+    length: usize,
+) -> HashMap<Resource, RealUnits> {
+    [
+        (Resource::ConcentratT1, RealUnits(members * length)),
+    ].iter().cloned().collect()
+}
+
+/// Что мусорщик нашел за ход во внешнем блоке
+fn roll_loot(dice: &mut Dice) -> (Resource, RealUnits) {
+    match d(dice, 1, 100) {
+        1..=30 => (Resource::ScrapT1, RealUnits(d(dice, 2, 6))),
+        31..=45 => (Resource::Concrete, RealUnits(d(dice, 2, 6))),
+        46..=60 => (Resource::BioRawT1, RealUnits(d(dice, 2, 6))),
+        61..=70 => (Resource::ScrapT2, RealUnits(d(dice, 1, 6))),
+        71..=78 => (Resource::BioRawT2, RealUnits(d(dice, 1, 6))),
+        79..=85 => (Resource::PolymerT1, RealUnits(d(dice, 1, 6))),
+        86..=90 => (Resource::ScrapT3, RealUnits(d(dice, 1, 3))),
+        91..=94 => (Resource::BioRawT3, RealUnits(d(dice, 1, 3))),
+        95..=98 => (random_slime(dice), RealUnits(d(dice, 1, 6))),
+        _ => (Resource::IsoConcrente, RealUnits(1)),
+    }
+}
+
+/// Отправить мусорщиков в вылазку на `length` ходов.
+/// Берутся свободные здоровые мусорщики, паек на всю вылазку
+/// списывается со склада сразу.
+pub fn send_expedition(
+    world: &mut World,
+    members: usize,
+    length: usize,
+) -> Result<Entity, SamosborError> {
+    if length == 0 {
+        return Err(SamosborError::InvalidExpeditionLength(length))
+    }
+    let mut stalkers_q = <(Entity, &Profession)>::query()
+        .filter(
+            !component::<OnExpedition>()
+            & !component::<Injured>()
+            & !component::<Mutated>()
+        );
+    let stalkers: Vec<Entity> = stalkers_q
+        .iter(world)
+        .filter(|(_, prof)| **prof == Profession::Stalker)
+        .map(|(e, _)| *e)
+        .collect();
+    if members == 0 || stalkers.len() < members {
+        return Err(SamosborError::NotEnoughPeople {
+            profession: Profession::Stalker,
+            required: members,
+            available: stalkers.len(),
+        })
+    }
+    writeoff_bunch(world, expedition_supplies(members, length))?;
+    let expedition = world.push((
+        Expedition {
+            turns_left: length,
+            length,
+            loot: HashMap::new(),
+        },
+    ));
+    for stalker in stalkers.iter().take(members) {
+        if let Some(mut entry) = world.entry(*stalker) {
+            entry.remove_component::<AssignedTask>();
+            entry.add_component(OnExpedition(expedition));
+        }
+    }
+    Ok(expedition)
+}
+
+/// Ход вылазок. Во внешнем блоке каждый ход можно
+/// погибнуть или покалечиться, каждый ход что-то находят.
+/// Кто дожил до конца вылазки - возвращается с добычей.
+pub fn expedition_tick(
+    world: &mut World,
+    resources: &mut Resources,
+) -> ExpeditionReport {
+    let mut report = ExpeditionReport::default();
+    let mut dice = resources
+        .get_mut::<Dice>()
+        .unwrap();
+
+    let mut members_q = <(Entity, &OnExpedition, Option<&Injured>)>::query();
+    let members: Vec<(Entity, Entity, bool)> = members_q
+        .iter(world)
+        .map(|(e, OnExpedition(exp), injured)| (*e, *exp, injured.is_some()))
+        .collect();
+    let mut killed: Vec<Entity> = Vec::new();
+    let mut injured: Vec<Entity> = Vec::new();
+    let mut found: Vec<(Entity, Resource, RealUnits)> = Vec::new();
+    for (member, expedition, already_injured) in members.iter() {
+        match d(&mut dice, 1, 20) {
+            1 => killed.push(*member),
+            // Раненый второй раз не выкарабкивается
+            2 | 3 if *already_injured => killed.push(*member),
+            2 | 3 => injured.push(*member),
+            _ => {
+                let (res, amount) = roll_loot(&mut dice);
+                found.push((*expedition, res, amount));
+            },
+        }
+    }
+    for member in killed.iter() {
+        world.remove(*member);
+    }
+    for member in injured.iter() {
+        if let Some(mut entry) = world.entry(*member) {
+            entry.add_component(Injured { turns_left: INJURY_TURNS });
+        }
+    }
+    report.killed = killed.len();
+    report.injured = injured.len();
+
+    // Едят взятый с собой паек
+    let mut eaters_q = <&mut Satiety>::query()
+        .filter(component::<OnExpedition>());
+    for sat in eaters_q.iter_mut(world) {
+        sat.0 = sat.0.saturating_add(HUNGER_PER_TURN);
+    }
+
+    // Найденное несут с собой, ход вылазки идет дальше
+    let mut expeditions_q = <(Entity, &mut Expedition)>::query();
+    let mut returning: Vec<(Entity, HashMap<Resource, RealUnits>)> = Vec::new();
+    for (entity, expedition) in expeditions_q.iter_mut(world) {
+        for (from, res, amount) in found.iter() {
+            if from == entity {
+                expedition
+                    .loot
+                    .entry(*res)
+                    .or_insert(RealUnits(0))
+                    .0 += amount.0;
+            }
+        }
+        expedition.turns_left = expedition.turns_left.saturating_sub(1);
+        if expedition.turns_left == 0 {
+            returning.push((*entity, expedition.loot.clone()));
+        }
+    }
+
+    for (expedition, loot) in returning.iter() {
+        let mut back_q = <(Entity, &OnExpedition)>::query();
+        let back: Vec<Entity> = back_q
+            .iter(world)
+            .filter(|(_, OnExpedition(exp))| exp == expedition)
+            .map(|(e, _)| *e)
+            .collect();
+        for member in back.iter() {
+            if let Some(mut entry) = world.entry(*member) {
+                entry.remove_component::<OnExpedition>();
+            }
+        }
+        report.returned += back.len();
+        world.remove(*expedition);
+        // Погибли все - добыча осталась во внешнем блоке
+        if back.is_empty() {
+            continue
+        }
+        for (res, amount) in loot.iter() {
+            // Что не влезло на склад - бросили у гермы
+            let dumped = put_or_dump(world, *res, *amount);
+            if dumped < *amount {
+                report.loot.push((*res, *amount - dumped));
+            }
+            if dumped > RealUnits(0) {
+                report.dumped.push((*res, dumped));
+            }
+        }
+    }
+    report
+}
+
+/// Сколько комрадов сейчас во внешнем блоке
+pub fn on_expedition(
+    world: &mut World,
+) -> usize {
+    let mut query = <&OnExpedition>::query();
+    query.iter(world).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::area::*;
+    use crate::colony::init_resources;
+
+    #[test]
+    fn test_zero_length_rejected() {
+        let mut world = World::default();
+        assert_eq!(
+            send_expedition(&mut world, 1, 0),
+            Err(SamosborError::InvalidExpeditionLength(0)),
+        );
+    }
+
+    #[test]
+    fn test_loot_kept_without_storage() {
+        let mut world = World::default();
+        let mut resources = init_resources(0);
+        let cell = spawn_ready_germ(&mut world, Tier::T1, AreaType::Living).unwrap();
+        let stalker = spawn_comrad(&mut world, Profession::Stalker, Tier::T1, MilitaryDep::None, SciSpec::None, cell).unwrap();
+        let expedition = world.push((
+            Expedition {
+                turns_left: 1,
+                length: 1,
+                loot: [(Resource::Concrete, RealUnits(50))].iter().cloned().collect(),
+            },
+        ));
+        if let Some(mut entry) = world.entry(stalker) {
+            entry.add_component(OnExpedition(expedition));
+        }
+        let report = expedition_tick(&mut world, &mut resources);
+        assert_eq!(report.returned, 1);
+        // Складов нет: все найденное брошено у гермы, но не пропало
+        let concrete = |list: &Vec<(Resource, RealUnits)>| list
+            .iter()
+            .filter(|(res, _)| *res == Resource::Concrete)
+            .map(|(_, amount)| amount.0)
+            .sum::<usize>();
+        assert_eq!(concrete(&report.loot), 0);
+        assert!(concrete(&report.dumped) >= 50);
+        assert_eq!(
+            how_much_we_have(&mut world, Resource::Concrete).0,
+            concrete(&report.dumped),
+        );
+    }
+}
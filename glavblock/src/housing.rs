@@ -0,0 +1,318 @@
+use std::collections::HashMap;
+
+use legion::*;
+use serde::{Deserialize, Serialize};
+
+use crate::core::*;
+use crate::area::*;
+use crate::people::*;
+use crate::production::*;
+use crate::expedition::OnExpedition;
+
+/// Насколько портится настроение за ход в переполненной ячейке
+const OVERCROWDING_MOOD: u8 = 1;
+
+/// Насколько портится настроение за ход у бездомного
+const HOMELESS_MOOD: u8 = 2;
+
+/// Комраду негде жить. Ночует в коридорах,
+/// ни к какому помещению не приписан.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Homeless ();
+
+/// Жилой фонд колонии. По нему видно, сколько жилячеек
+/// не хватает.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HousingReport {
+    /// Площадь готовых жилячеек
+    pub capacity: usize,
+    /// Сколько из нее занято
+    pub occupied: usize,
+    pub homeless: usize,
+    /// Сколько площади нужно бездомным
+    pub homeless_area: usize,
+    /// Ячейки, где живут сверх вместимости
+    pub overcrowded: usize,
+}
+
+impl HousingReport {
+    /// Сколько площади жилячеек не хватает всем
+    pub fn shortage(&self) -> usize {
+        (self.occupied + self.homeless_area).saturating_sub(self.capacity)
+    }
+}
+
+/// Где комрад может жить: в жилячейке,
+/// ликвидаторы - еще и в казарме.
+fn can_live_in(prof: Option<Profession>, area: AreaType) -> bool {
+    area == AreaType::Living
+        || (area == AreaType::Military && prof == Some(Profession::Likvidator))
+}
+
+/// Готовые помещения, в которых можно жить
+fn dwellings(
+    world: &World,
+) -> HashMap<Entity, AreaType> {
+    let mut query = <(Entity, &AreaType, &StationaryStatus)>::query();
+    query
+        .iter(world)
+        .filter(|(_, area, status)|
+                (**area == AreaType::Living || **area == AreaType::Military)
+                && **status == StationaryStatus::Ready)
+        .map(|(e, area, _)| (*e, *area))
+        .collect()
+}
+
+/// Поселить комрада в помещение
+fn move_in(
+    world: &mut World,
+    comrad: Entity,
+    room: Entity,
+) {
+    if let Some(mut entry) = world.entry(comrad) {
+        entry.remove_component::<Homeless>();
+        entry.add_component(BelongsToRoom(room));
+    }
+}
+
+/// Найти комраду жилье: ликвидаторам сначала казарму,
+/// остальным - жилячейку со свободным местом.
+pub fn settle(
+    world: &mut World,
+    comrad: Entity,
+) -> Result<Entity, SamosborError> {
+    let (prof, occupied) = {
+        let entry = world
+            .entry_ref(comrad)
+            .map_err(|_| SamosborError::NoSuchEntity(comrad))?;
+        let occupied = *entry
+            .get_component::<AreaOccupied>()
+            .map_err(|_| SamosborError::NoSuchEntity(comrad))?;
+        (entry.get_component::<Profession>().ok().cloned(), occupied)
+    };
+    let room = if prof == Some(Profession::Likvidator) {
+        get_sufficent_room(world, occupied, AreaType::Military)
+            .or_else(|| get_sufficent_room(world, occupied, AreaType::Living))
+    } else {
+        get_sufficent_room(world, occupied, AreaType::Living)
+    };
+    let room = room.ok_or(SamosborError::NoEmptyArea {
+        area_type: AreaType::Living,
+        required: occupied,
+    })?;
+    move_in(world, comrad, room);
+    Ok(room)
+}
+
+/// Найти комраду жилье, а не нашлось - он бездомный
+pub(crate) fn settle_or_evict(
+    world: &mut World,
+    comrad: Entity,
+) {
+    if settle(world, comrad).is_err() {
+        if let Some(mut entry) = world.entry(comrad) {
+            entry.remove_component::<BelongsToRoom>();
+            entry.add_component(Homeless());
+        }
+    }
+}
+
+/// Переселить комрада в указанную жилячейку
+pub fn assign_home(
+    world: &mut World,
+    comrad: Entity,
+    room: Entity,
+) -> Result<(), SamosborError> {
+    let (prof, occupied, home) = {
+        let entry = world
+            .entry_ref(comrad)
+            .map_err(|_| SamosborError::NoSuchEntity(comrad))?;
+        let occupied = *entry
+            .get_component::<AreaOccupied>()
+            .map_err(|_| SamosborError::NoSuchEntity(comrad))?;
+        (
+            entry.get_component::<Profession>().ok().cloned(),
+            occupied,
+            entry.get_component::<BelongsToRoom>().ok().map(|BelongsToRoom(r)| *r),
+        )
+    };
+    let free = get_room_free_space(world, room)?;
+    let area = *world
+        .entry_ref(room)
+        .map_err(|_| SamosborError::NoSuchEntity(room))?
+        .get_component::<AreaType>()
+        .map_err(|_| SamosborError::NotARoom(room))?;
+    if !can_live_in(prof, area) {
+        return Err(SamosborError::WrongAreaType {
+            room,
+            expected: AreaType::Living,
+            actual: area,
+        })
+    }
+    if !room_is_ready(world, room) {
+        return Err(SamosborError::RoomNotReady(room))
+    }
+    if home == Some(room) {
+        return Ok(())
+    }
+    if free < occupied.0 as i32 {
+        return Err(SamosborError::NotEnoughArea {
+            room,
+            required: occupied,
+            free,
+        })
+    }
+    move_in(world, comrad, room);
+    Ok(())
+}
+
+/// Жилищный вопрос. Тех, чье жилье снесли или не достроено,
+/// переселяют куда есть место, кому места нет - становятся бездомными.
+/// Теснота и жизнь в коридорах портят настроение.
+pub fn housing_tick(
+    world: &mut World,
+    _resources: &mut Resources,
+) {
+    let dwellings = dwellings(world);
+
+    // Ушедшие в вылазку числятся за своим жильем
+    let mut residents_q = <(Entity, Option<&Profession>, Option<&BelongsToRoom>)>::query()
+        .filter(
+            component::<Satiety>()
+            & component::<AreaOccupied>()
+            & !component::<OnExpedition>()
+        );
+    let unhoused: Vec<Entity> = residents_q
+        .iter(world)
+        .filter(|(_, prof, home)| match home {
+            Some(BelongsToRoom(room)) => match dwellings.get(room) {
+                Some(area) => !can_live_in(prof.cloned(), *area),
+                None => true,
+            },
+            None => true,
+        })
+        .map(|(e, _, _)| *e)
+        .collect();
+    for comrad in unhoused.iter() {
+        settle_or_evict(world, *comrad);
+    }
+
+    // Теснота. Считается только в жилячейках, как и в сводке:
+    // в казарме ликвидаторы живут по уставу.
+    let mut crowded: Vec<Entity> = Vec::new();
+    for (room, area) in dwellings.iter() {
+        if *area == AreaType::Living && get_room_free_space(world, *room).unwrap_or(0) < 0 {
+            crowded.push(*room);
+        }
+    }
+    let mut people_q = <(&BelongsToRoom, &mut Mood)>::query()
+        .filter(component::<Satiety>());
+    for (BelongsToRoom(room), mood) in people_q.iter_mut(world) {
+        if crowded.contains(room) {
+            mood.lower(OVERCROWDING_MOOD);
+        }
+    }
+    let mut homeless_q = <&mut Mood>::query()
+        .filter(component::<Homeless>());
+    for mood in homeless_q.iter_mut(world) {
+        mood.lower(HOMELESS_MOOD);
+    }
+}
+
+/// Сводка по жилью
+pub fn housing_report(
+    world: &mut World,
+) -> HousingReport {
+    let mut report = HousingReport::default();
+    let mut cells_q = <(Entity, &AreaType, &AreaCapacity, &StationaryStatus)>::query();
+    let cells: Vec<(Entity, usize)> = cells_q
+        .iter(world)
+        .filter(|(_, area, _, status)|
+                **area == AreaType::Living
+                && **status == StationaryStatus::Ready)
+        .map(|(e, _, AreaCapacity(capacity), _)| (*e, *capacity))
+        .collect();
+    for (cell, capacity) in cells.iter() {
+        let free = get_room_free_space(world, *cell).unwrap_or(0);
+        report.capacity += capacity;
+        report.occupied += (*capacity as i32 - free) as usize;
+        if free < 0 {
+            report.overcrowded += 1;
+        }
+    }
+    let mut homeless_q = <&AreaOccupied>::query()
+        .filter(component::<Homeless>());
+    for AreaOccupied(area) in homeless_q.iter(world) {
+        report.homeless += 1;
+        report.homeless_area += area;
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn worker(world: &mut World, room: Entity) -> Entity {
+        spawn_comrad(world, Profession::Worker, Tier::T1, MilitaryDep::None, SciSpec::None, room).unwrap()
+    }
+
+    #[test]
+    fn test_residents_of_lost_room_rehoused() {
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        let old = spawn_ready_germ(&mut world, Tier::T1, AreaType::Living).unwrap();
+        let new = spawn_ready_germ(&mut world, Tier::T1, AreaType::Living).unwrap();
+        let first = worker(&mut world, old);
+        let second = worker(&mut world, old);
+        // Одного из двух впишут в остаток новой ячейки, второй - в коридор
+        for _ in 0..2 {
+            worker(&mut world, new);
+        }
+        world.remove(old);
+        housing_tick(&mut world, &mut resources);
+        let report = housing_report(&mut world);
+        assert_eq!(report.homeless, 1);
+        assert_eq!(report.homeless_area, COMRAD_RENTED_PLACE);
+        assert_eq!(report.occupied, 3 * COMRAD_RENTED_PLACE);
+        let homeless: Vec<bool> = [first, second]
+            .iter()
+            .map(|c| world.entry_ref(*c).unwrap().get_component::<Homeless>().is_ok())
+            .collect();
+        assert_eq!(homeless.iter().filter(|h| **h).count(), 1);
+    }
+
+    #[test]
+    fn test_full_barracks_not_overcrowding() {
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        let barracks = spawn_ready_germ(&mut world, Tier::T1, AreaType::Military).unwrap();
+        let cell = spawn_ready_germ(&mut world, Tier::T1, AreaType::Living).unwrap();
+        let mut soldiers = Vec::new();
+        let mut workers = Vec::new();
+        for _ in 0..4 {
+            soldiers.push(spawn_comrad(&mut world, Profession::Likvidator, Tier::T1, MilitaryDep::OBCU, SciSpec::None, barracks).unwrap());
+            workers.push(worker(&mut world, cell));
+        }
+        housing_tick(&mut world, &mut resources);
+        let mood = |world: &World, comrad: &Entity| *world.entry_ref(*comrad).unwrap().get_component::<Mood>().unwrap();
+        assert!(soldiers.iter().all(|c| mood(&world, c) == Mood(5)));
+        assert!(workers.iter().all(|c| mood(&world, c) == Mood(5 - OVERCROWDING_MOOD)));
+    }
+
+    #[test]
+    fn test_workers_dont_live_in_barracks() {
+        let mut world = World::default();
+        let cell = spawn_ready_germ(&mut world, Tier::T1, AreaType::Living).unwrap();
+        let barracks = spawn_ready_germ(&mut world, Tier::T1, AreaType::Military).unwrap();
+        let comrad = worker(&mut world, cell);
+        assert_eq!(
+            assign_home(&mut world, comrad, barracks),
+            Err(SamosborError::WrongAreaType {
+                room: barracks,
+                expected: AreaType::Living,
+                actual: AreaType::Military,
+            }),
+        );
+    }
+}
@@ -0,0 +1,211 @@
+//! Симуляция колонии Главблока.
+//!
+//! Окно на macroquad собирается отдельным бинарником под фичей `ui`,
+//! здесь только экономика: помещения, люди, склады, производство и ход.
+//! Стабильное API переэкспортировано из корня крейта,
+//! модули открыты для инструментов, которым нужны подробности.
+
+pub mod core;
+pub mod production;
+pub mod resources;
+pub mod storage;
+pub mod people;
+pub mod area;
+pub mod turn;
+pub mod recipes;
+pub mod samosbor;
+pub mod colony;
+pub mod simulation;
+pub mod serialization;
+pub mod definitions;
+pub mod science;
+pub mod expedition;
+pub mod military;
+pub mod unrest;
+pub mod nutrition;
+pub mod population;
+pub mod housing;
+pub mod utilities;
+pub mod maintenance;
+pub mod deconstruction;
+pub mod tasks;
+
+pub use crate::core::{
+    d,
+    Dice,
+    SamosborError,
+    Tier,
+};
+pub use crate::area::{
+    AreaType,
+    AreaCapacity,
+    AreaOccupied,
+    BelongsToRoom,
+    get_room_free_space,
+    room_is_ready,
+    get_sufficent_room,
+};
+pub use crate::people::{
+    MilitaryDep,
+    SciSpec,
+    Profession,
+    Satiety,
+    Mood,
+    MAX_MOOD,
+    Experience,
+    spawn_comrad,
+    people_by_profession,
+    block_mood,
+    block_satiety,
+};
+pub use crate::production::{
+    Stationary,
+    StationaryStatus,
+    TaskMeta,
+    TaskPriority,
+    TaskTotal,
+    Paused,
+    ResumeStatus,
+    BuildPower,
+    AssignedTask,
+    busy_comrads,
+    idle_comrads,
+    install_germ,
+    spawn_ready_stationary,
+    dismantle_stationary,
+    spawn_ready_germ,
+    start_build_task,
+    stationary_areas,
+};
+pub use crate::resources::Resource;
+pub use crate::recipes::{
+    Recipe,
+    start_recipe_task,
+};
+pub use crate::samosbor::{
+    SamosborClock,
+    SamosborReport,
+    Contamination,
+    samosbor,
+    contaminated_rooms,
+};
+pub use crate::storage::{
+    RealUnits,
+    ContainerKind,
+    StorageEntry,
+    StorageLedger,
+    storage_ledger,
+    room_storage,
+    put_resource,
+    writeoff_bunch,
+    how_much_we_have,
+    what_we_have,
+    enough_resources,
+};
+pub use crate::turn::turn;
+pub use crate::colony::{
+    init_colony,
+    init_resources,
+};
+pub use crate::simulation::{
+    TurnReport,
+    turn_report,
+    simulate,
+};
+pub use crate::definitions::{
+    Definitions,
+    DEFINITIONS_FILE,
+    load_definitions,
+    load_definitions_or_builtin,
+};
+pub use crate::science::{
+    Tech,
+    ResearchStatus,
+    start_research,
+    researched_techs,
+    is_researched,
+};
+pub use crate::expedition::{
+    Expedition,
+    ExpeditionReport,
+    send_expedition,
+    on_expedition,
+};
+pub use crate::military::{
+    Squad,
+    SquadRole,
+    SquadMember,
+    form_squad,
+    reinforce_squad,
+    disband_squad,
+    dispatch_squad,
+    recall_squad,
+    equip_squad,
+    squad_members,
+    squad_strength,
+};
+pub use crate::unrest::{
+    OnStrike,
+    Riot,
+    UnrestReport,
+    mood_buildpower,
+    strikers,
+    riots,
+};
+pub use crate::nutrition::{
+    Ration,
+    RationPolicy,
+    satiety_buildpower,
+};
+pub use crate::population::{
+    Child,
+    RefugeeGroup,
+    PopulationReport,
+    pending_refugees,
+    accept_refugees,
+    reject_refugees,
+    children,
+};
+pub use crate::housing::{
+    Homeless,
+    HousingReport,
+    settle,
+    assign_home,
+    housing_report,
+};
+pub use crate::utilities::{
+    Utility,
+    UtilityConnections,
+    Unsupplied,
+    connect_room,
+    disconnect_room,
+    room_supply,
+    unsupplied_stationaries,
+};
+pub use crate::maintenance::{
+    Durability,
+    MaintenanceReport,
+    MAX_DURABILITY,
+    start_repair,
+    broken_stationaries,
+};
+pub use crate::deconstruction::{
+    RelocateTo,
+    deconstruction_refund,
+    start_deconstruction,
+    start_relocation,
+    start_germ_deconstruction,
+};
+pub use crate::tasks::{
+    TaskOwner,
+    TaskInfo,
+    list_tasks,
+    set_task_priority,
+    pause_task,
+    resume_task,
+    cancel_task,
+};
+pub use crate::serialization::{
+    save_colony,
+    load_colony,
+};
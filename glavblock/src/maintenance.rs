@@ -0,0 +1,255 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use legion::*;
+use serde::{Deserialize, Serialize};
+
+use crate::core::*;
+use crate::people::*;
+use crate::production::*;
+use crate::resources::*;
+use crate::storage::*;
+use crate::utilities::utility_node;
+
+/// Износ новой или только что отремонтированной стационарки
+pub const MAX_DURABILITY: usize = 100;
+
+/// Сколько износа дает смена работы на стационарке
+pub const WEAR_PER_SHIFT: usize = 1;
+
+/// Во сколько раз износ меньше шанса поломки в процентах:
+/// на последней единице прочности - 9% в ход,
+/// на нуле стационарка ломается наверняка
+const BREAKDOWN_DIVISOR: usize = 10;
+
+/// Запас прочности стационарки. Падает от работы,
+/// на нуле стационарка ломается.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Durability (pub usize);
+
+/// Что сломалось за ход
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MaintenanceReport {
+    pub broken: Vec<(Entity, Stationary)>,
+}
+
+/// Запчасти на ремонт стационарки
+pub fn repair_resources(
+    stationary: Stationary,
+) -> HashMap<Resource, RealUnits> {
+    match stationary {
+        Stationary::None | Stationary::Rack | Stationary::Classroom => HashMap::new(),
+        Stationary::BenchToolT2 | Stationary::LabT2 => [
+            (Resource::ComponentT1, RealUnits (2)),
+            (Resource::ComponentT2, RealUnits (1)),
+        ].iter().cloned().collect(),
+        Stationary::BenchToolT3 | Stationary::LabT3 | Stationary::NeuroTerminal => [
+            (Resource::ComponentT2, RealUnits (3)),
+        ].iter().cloned().collect(),
+        _ => [
+            (Resource::ComponentT1, RealUnits (2)),
+        ].iter().cloned().collect(),
+    }
+}
+
+/// Кто чинит: сложное оборудование - только опытные
+fn repair_tier(stationary: Stationary) -> Tier {
+    match stationary {
+        Stationary::BenchToolT3 | Stationary::LabT3 | Stationary::NeuroTerminal => Tier::T2,
+        _ => Tier::T1,
+    }
+}
+
+/// Работа на ремонт: половина работы по постройке
+pub fn repair_requirements(
+    stationary: Stationary,
+) -> Vec<TaskMeta> {
+    let build: usize = stationary_requirements(stationary)
+        .iter()
+        .map(|task| task.bp.0)
+        .sum();
+    vec![
+        TaskMeta {
+            prof: Profession::Worker,
+            tier: repair_tier(stationary),
+            bp: BuildPower(std::cmp::max(build / 2, 5)),
+            stationary: Stationary::None,
+            sci_spec: SciSpec::None,
+        },
+    ]
+}
+
+/// Запустить ремонт сломанной или изношенной стационарки.
+/// Запчасти списываются сразу, на время ремонта стационарка стоит.
+pub fn start_repair(
+    world: &mut World,
+    stationary: Entity,
+    priority: TaskPriority,
+) -> Result<(), SamosborError> {
+    let (kind, status, durability) = {
+        let entry = world
+            .entry_ref(stationary)
+            .map_err(|_| SamosborError::NoSuchEntity(stationary))?;
+        (
+            *entry
+                .get_component::<Stationary>()
+                .map_err(|_| SamosborError::NoSuchEntity(stationary))?,
+            *entry
+                .get_component::<StationaryStatus>()
+                .map_err(|_| SamosborError::NoSuchEntity(stationary))?,
+            entry
+                .get_component::<Durability>()
+                .copied()
+                .unwrap_or(Durability(MAX_DURABILITY)),
+        )
+    };
+    let needs_repair = status == StationaryStatus::Broken
+        || (status == StationaryStatus::Ready
+            && durability < Durability(MAX_DURABILITY));
+    if !needs_repair {
+        return Err(SamosborError::NoRepairNeeded(stationary))
+    }
+    writeoff_bunch(world, repair_resources(kind))?;
+    if let Some(mut entry) = world.entry(stationary) {
+        entry.add_component(ResumeStatus(status));
+        entry.add_component(StationaryStatus::UnderRepair);
+    }
+    for task_meta in repair_requirements(kind).iter() {
+        push_task(world, BelongsToStationary(stationary), *task_meta, priority);
+    }
+    Ok(())
+}
+
+/// Износ работающих узлов коммуникаций и поломки.
+/// Чем сильнее износ, тем выше шанс что стационарка сломается,
+/// изношенная в ноль ломается наверняка.
+pub fn maintenance_tick(
+    world: &mut World,
+    resources: &mut Resources,
+) -> MaintenanceReport {
+    let mut report = MaintenanceReport::default();
+    let mut dice = resources
+        .get_mut::<Dice>()
+        .unwrap();
+
+    // Узлы коммуникаций работают без перерыва
+    let mut nodes_q = <(&Stationary, &StationaryStatus, &mut Durability)>::query();
+    for (stationary, status, durability) in nodes_q.iter_mut(world) {
+        if *status == StationaryStatus::Ready && utility_node(*stationary).is_some() {
+            durability.0 = durability.0.saturating_sub(WEAR_PER_SHIFT);
+        }
+    }
+
+    let mut stationary_q = <(Entity, &Stationary, &StationaryStatus, &Durability)>::query();
+    let worn: Vec<(Entity, Stationary, Durability)> = stationary_q
+        .iter(world)
+        .filter(|(_, _, status, durability)|
+                **status == StationaryStatus::Ready
+                && **durability < Durability(MAX_DURABILITY))
+        .map(|(e, stationary, _, durability)| (*e, *stationary, *durability))
+        .collect();
+    for (entity, stationary, Durability(durability)) in worn.iter() {
+        let chance = (MAX_DURABILITY - durability) / BREAKDOWN_DIVISOR;
+        if *durability == 0 || d(&mut dice, 1, 100) <= chance {
+            if let Some(mut entry) = world.entry(*entity) {
+                entry.add_component(StationaryStatus::Broken);
+            }
+            report.broken.push((*entity, *stationary));
+        }
+    }
+    report
+}
+
+/// Ремонт без открытых задач закончен:
+/// стационарка снова в строю и как новая
+pub(crate) fn complete_repairs(
+    world: &mut World,
+    _resources: &mut Resources,
+) -> usize {
+    let mut open_q = <&BelongsToStationary>::query()
+        .filter(component::<TaskMeta>());
+    let open: HashSet<Entity> = open_q
+        .iter(world)
+        .map(|BelongsToStationary(e)| *e)
+        .collect();
+    let mut repairs_q = <(Entity, &StationaryStatus)>::query();
+    let repaired: Vec<Entity> = repairs_q
+        .iter(world)
+        .filter(|(e, status)|
+                **status == StationaryStatus::UnderRepair
+                && !open.contains(*e))
+        .map(|(e, _)| *e)
+        .collect();
+    for entity in repaired.iter() {
+        if let Some(mut entry) = world.entry(*entity) {
+            entry.remove_component::<ResumeStatus>();
+            entry.add_component(StationaryStatus::Ready);
+            entry.add_component(Durability(MAX_DURABILITY));
+        }
+    }
+    repaired.len()
+}
+
+/// Сколько стационарок сломано или в ремонте
+pub fn broken_stationaries(
+    world: &mut World,
+) -> usize {
+    let mut query = <&StationaryStatus>::query()
+        .filter(component::<Stationary>());
+    query
+        .iter(world)
+        .filter(|status|
+                **status == StationaryStatus::Broken
+                || **status == StationaryStatus::UnderRepair)
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::area::*;
+    use crate::colony::init_resources;
+
+    #[test]
+    fn test_worn_out_breaks() {
+        let mut world = World::default();
+        let mut resources = init_resources(0);
+        let room = spawn_ready_germ(&mut world, Tier::T1, AreaType::Industrial).unwrap();
+        let bench = spawn_ready_stationary(&mut world, Stationary::BenchToolT1, room);
+        if let Some(mut entry) = world.entry(bench) {
+            entry.add_component(Durability(0));
+        }
+        let report = maintenance_tick(&mut world, &mut resources);
+        assert_eq!(report.broken, vec![(bench, Stationary::BenchToolT1)]);
+        assert_eq!(broken_stationaries(&mut world), 1);
+    }
+
+    #[test]
+    fn test_repair_restores_durability() {
+        let mut world = World::default();
+        let mut resources = init_resources(0);
+        spawn_ready_germ(&mut world, Tier::T1, AreaType::Party).unwrap();
+        let workshop = spawn_ready_germ(&mut world, Tier::T1, AreaType::Industrial).unwrap();
+        let bench = spawn_ready_stationary(&mut world, Stationary::BenchToolT1, workshop);
+        assert_eq!(
+            start_repair(&mut world, bench, TaskPriority(0)),
+            Err(SamosborError::NoRepairNeeded(bench)),
+        );
+        if let Some(mut entry) = world.entry(bench) {
+            entry.add_component(StationaryStatus::Broken);
+        }
+        put_resource(&mut world, Resource::ComponentT1, RealUnits(2)).unwrap();
+        start_repair(&mut world, bench, TaskPriority(0)).unwrap();
+        assert_eq!(how_much_we_have(&mut world, Resource::ComponentT1), RealUnits(0));
+        // Работу по ремонту сделали
+        let mut tasks_q = <&mut TaskMeta>::query();
+        for task in tasks_q.iter_mut(&mut world) {
+            task.bp = BuildPower(0);
+        }
+        crate::turn::clean_up_completed_tasks(&mut world, &mut resources);
+        assert_eq!(complete_repairs(&mut world, &mut resources), 1);
+        let entry = world.entry_ref(bench).unwrap();
+        assert_eq!(*entry.get_component::<StationaryStatus>().unwrap(), StationaryStatus::Ready);
+        assert_eq!(*entry.get_component::<Durability>().unwrap(), Durability(MAX_DURABILITY));
+    }
+}
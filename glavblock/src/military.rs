@@ -0,0 +1,412 @@
+use std::collections::HashMap;
+
+use legion::*;
+use serde::{Deserialize, Serialize};
+
+use crate::core::*;
+use crate::area::*;
+use crate::people::*;
+use crate::resources::*;
+use crate::storage::*;
+use crate::samosbor::*;
+use crate::science::*;
+use crate::unrest::OnStrike;
+
+/// Отряд ликвидаторов. Приписан к казарме (`BelongsToRoom`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Squad {
+    pub dep: MilitaryDep,
+}
+
+/// Роль бойца в отряде
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SquadRole {
+    Sergeant, // Сержант. Командует, сам тоже с огнеметом.
+    Flamethrower, // Огнеметчик. Выжигает слизь и тварей.
+    Rake, // Граблист. Сгребает и выносит слизь.
+    Iskra, // Расчет "Искры". Электроразрядник против крупных тварей.
+    Granit, // Расчет "Гранита". Тяжелый щит, держит проход.
+}
+
+/// Боец приписан к отряду
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SquadMember {
+    pub squad: Entity,
+    pub role: SquadRole,
+}
+
+/// Боец получил снаряжение своей роли
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Equipped ();
+
+/// Отряд отправлен в помещение
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DispatchedTo (pub Entity);
+
+/// Что уходит на снаряжение бойца
+pub fn role_equipment(
+    role: SquadRole,
+) -> HashMap<Resource, RealUnits> {
+    match role {
+        SquadRole::Sergeant => [
+            (Resource::ComponentT1, RealUnits (2)),
+            (Resource::PolymerT2, RealUnits (1)),
+        ].iter().cloned().collect(),
+        SquadRole::Flamethrower => [
+            (Resource::ComponentT1, RealUnits (2)),
+            (Resource::PolymerT2, RealUnits (1)),
+        ].iter().cloned().collect(),
+        SquadRole::Rake => [
+            (Resource::ScrapT1, RealUnits (2)),
+        ].iter().cloned().collect(),
+        SquadRole::Iskra => [
+            (Resource::ComponentT2, RealUnits (1)),
+            (Resource::ComponentT1, RealUnits (1)),
+        ].iter().cloned().collect(),
+        SquadRole::Granit => [
+            (Resource::ScrapT1, RealUnits (4)),
+            (Resource::PolymerT2, RealUnits (1)),
+        ].iter().cloned().collect(),
+    }
+}
+
+/// Боевая ценность бойца в роли, со снаряжением
+fn role_strength(role: SquadRole) -> usize {
+    match role {
+        SquadRole::Sergeant => 4,
+        SquadRole::Flamethrower => 3,
+        SquadRole::Rake => 2,
+        SquadRole::Iskra => 3,
+        SquadRole::Granit => 4,
+    }
+}
+
+/// Сколько единиц слизи отряд убирает за ход
+/// на единицу силы
+const CLEANUP_UNITS_PER_STRENGTH: usize = 1;
+
+/// Собрать пустой отряд при казарме
+pub fn form_squad(
+    world: &mut World,
+    dep: MilitaryDep,
+    barracks: Entity,
+) -> Result<Entity, SamosborError> {
+    if dep == MilitaryDep::None {
+        return Err(SamosborError::NotADepartment(dep))
+    }
+    get_room_free_space(world, barracks)?;
+    let area = *world
+        .entry_ref(barracks)
+        .map_err(|_| SamosborError::NoSuchEntity(barracks))?
+        .get_component::<AreaType>()
+        .map_err(|_| SamosborError::NotARoom(barracks))?;
+    if area != AreaType::Military {
+        return Err(SamosborError::WrongAreaType {
+            room: barracks,
+            expected: AreaType::Military,
+            actual: area,
+        })
+    }
+    Ok(world.push((
+        Squad { dep },
+        BelongsToRoom(barracks),
+    )))
+}
+
+/// Отдел, к которому относится отряд
+fn squad_dep(
+    world: &World,
+    squad: Entity,
+) -> Result<MilitaryDep, SamosborError> {
+    world
+        .entry_ref(squad)
+        .map_err(|_| SamosborError::NoSuchEntity(squad))?
+        .get_component::<Squad>()
+        .map(|squad| squad.dep)
+        .map_err(|_| SamosborError::NotASquad(squad))
+}
+
+/// Включить ликвидатора в отряд на роль.
+/// Боец должен быть из того же отдела и не состоять в другом отряде.
+pub fn reinforce_squad(
+    world: &mut World,
+    squad: Entity,
+    comrad: Entity,
+    role: SquadRole,
+) -> Result<(), SamosborError> {
+    let dep = squad_dep(world, squad)?;
+    let mut entry = world
+        .entry(comrad)
+        .ok_or(SamosborError::NoSuchEntity(comrad))?;
+    let fits = entry
+        .get_component::<Profession>()
+        .map(|prof| *prof == Profession::Likvidator)
+        .unwrap_or(false)
+        && entry
+        .get_component::<MilitaryDep>()
+        .map(|mdep| *mdep == dep)
+        .unwrap_or(false);
+    if !fits {
+        return Err(SamosborError::NotASoldier(comrad))
+    }
+    if entry.get_component::<SquadMember>().is_ok() {
+        return Err(SamosborError::AlreadyInSquad(comrad))
+    }
+    entry.add_component(SquadMember { squad, role });
+    Ok(())
+}
+
+/// Бойцы отряда
+pub fn squad_members(
+    world: &World,
+    squad: Entity,
+) -> Vec<Entity> {
+    let mut query = <(Entity, &SquadMember)>::query();
+    query
+        .iter(world)
+        .filter(|(_, member)| member.squad == squad)
+        .map(|(e, _)| *e)
+        .collect()
+}
+
+/// Распустить отряд. Бойцы остаются в казарме без отряда,
+/// снаряжение остается при них.
+pub fn disband_squad(
+    world: &mut World,
+    squad: Entity,
+) -> Result<(), SamosborError> {
+    squad_dep(world, squad)?;
+    for member in squad_members(world, squad).iter() {
+        if let Some(mut entry) = world.entry(*member) {
+            entry.remove_component::<SquadMember>();
+        }
+    }
+    world.remove(squad);
+    Ok(())
+}
+
+/// Отправить отряд в помещение
+pub fn dispatch_squad(
+    world: &mut World,
+    squad: Entity,
+    room: Entity,
+) -> Result<(), SamosborError> {
+    squad_dep(world, squad)?;
+    get_room_free_space(world, room)?;
+    if let Some(mut entry) = world.entry(squad) {
+        entry.add_component(DispatchedTo(room));
+    }
+    Ok(())
+}
+
+/// Вернуть отряд в казарму
+pub fn recall_squad(
+    world: &mut World,
+    squad: Entity,
+) -> Result<(), SamosborError> {
+    squad_dep(world, squad)?;
+    if let Some(mut entry) = world.entry(squad) {
+        entry.remove_component::<DispatchedTo>();
+    }
+    Ok(())
+}
+
+/// Выдать снаряжение тем бойцам отряда, у кого его нет,
+/// пока хватает ресурсов. Возвращает сколько бойцов снаряжено.
+pub fn equip_squad(
+    world: &mut World,
+    squad: Entity,
+) -> Result<usize, SamosborError> {
+    squad_dep(world, squad)?;
+    let mut members_q = <(Entity, &SquadMember)>::query()
+        .filter(!component::<Equipped>());
+    let unequipped: Vec<(Entity, SquadRole)> = members_q
+        .iter(world)
+        .filter(|(_, member)| member.squad == squad)
+        .map(|(e, member)| (*e, member.role))
+        .collect();
+    let mut equipped = 0;
+    for (member, role) in unequipped.iter() {
+        if writeoff_bunch(world, role_equipment(*role)).is_err() {
+            break
+        }
+        if let Some(mut entry) = world.entry(*member) {
+            entry.add_component(Equipped());
+        }
+        equipped += 1;
+    }
+    Ok(equipped)
+}
+
+/// Сила отрядов. Считаются бойцы в строю:
+/// без снаряжения боец стоит половину, раненые, мутанты
+/// и забастовщики не в счет.
+pub fn squad_strength(
+    world: &World,
+) -> HashMap<Entity, usize> {
+    let mut result: HashMap<Entity, usize> = HashMap::new();
+    let mut query = <(&SquadMember, &Tier, Option<&Equipped>)>::query()
+        .filter(
+            !component::<Injured>()
+            & !component::<Mutated>()
+            & !component::<OnStrike>()
+        );
+    for (member, tier, equipped) in query.iter(world) {
+        let base = role_strength(member.role) * match tier {
+            Tier::T3 => 3,
+            Tier::T2 => 2,
+            _ => 1,
+        };
+        let strength = if equipped.is_some() { base } else { base / 2 };
+        *result.entry(member.squad).or_insert(0) += strength;
+    }
+    result
+}
+
+/// Где сейчас стоит отряд: в помещении куда отправлен
+/// или в своей казарме
+fn squad_positions(
+    world: &World,
+) -> Vec<(Entity, MilitaryDep, Entity)> {
+    let mut query = <(Entity, &Squad, &BelongsToRoom, Option<&DispatchedTo>)>::query();
+    query
+        .iter(world)
+        .map(|(e, squad, BelongsToRoom(home), dispatched)| (
+            *e,
+            squad.dep,
+            dispatched.map(|DispatchedTo(room)| *room).unwrap_or(*home),
+        ))
+        .collect()
+}
+
+/// Сколько силы стоит в каждом помещении
+pub fn room_defense(
+    world: &World,
+) -> HashMap<Entity, usize> {
+    let strength = squad_strength(world);
+    let mut result: HashMap<Entity, usize> = HashMap::new();
+    for (squad, _, room) in squad_positions(world).iter() {
+        *result.entry(*room).or_insert(0) += strength
+            .get(squad)
+            .cloned()
+            .unwrap_or(0);
+    }
+    result
+}
+
+/// Отряды ОЛПС за работой. Свободные отряды сами выходят
+/// на залитые помещения, отряд в залитом помещении убирает слизь
+/// по своей силе. Собранная слизь идет в чаны.
+pub fn squads_tick(
+    world: &mut World,
+    _resources: &mut Resources,
+) {
+    let mut contaminated_q = <(Entity, &Contamination)>::query();
+    let mut contaminated: Vec<Entity> = contaminated_q
+        .iter(world)
+        .map(|(e, _)| *e)
+        .collect();
+
+    // Свободные отряды разбирают помещения, где еще никого нет
+    let mut dispatched_q = <&DispatchedTo>::query();
+    let covered: Vec<Entity> = dispatched_q
+        .iter(world)
+        .map(|DispatchedTo(room)| *room)
+        .collect();
+    contaminated.retain(|room| !covered.contains(room));
+    let mut free_squads_q = <(Entity, &Squad)>::query()
+        .filter(!component::<DispatchedTo>());
+    let free_squads: Vec<Entity> = free_squads_q
+        .iter(world)
+        .filter(|(_, squad)| squad.dep == MilitaryDep::OLPS)
+        .map(|(e, _)| *e)
+        .collect();
+    for (squad, room) in free_squads.iter().zip(contaminated.iter()) {
+        if let Some(mut entry) = world.entry(*squad) {
+            entry.add_component(DispatchedTo(*room));
+        }
+    }
+
+    // Зачистка
+    let strength = squad_strength(world);
+    let recycling = is_researched(world, Tech::SlimeRecycling);
+    let mut cleaned: Vec<(Entity, Resource, RealUnits)> = Vec::new();
+    let mut finished: Vec<Entity> = Vec::new();
+    for (squad, dep, room) in squad_positions(world).iter() {
+        if *dep != MilitaryDep::OLPS {
+            continue
+        }
+        let units = strength.get(squad).cloned().unwrap_or(0)
+            * CLEANUP_UNITS_PER_STRENGTH;
+        if units == 0 {
+            continue
+        }
+        if let Some(mut entry) = world.entry(*room) {
+            if let Ok(contamination) = entry.get_component_mut::<Contamination>() {
+                let removed = std::cmp::min(units, contamination.amount.0);
+                contamination.amount.0 -= removed;
+                cleaned.push((*room, contamination.slime, RealUnits(removed)));
+                if contamination.amount.0 == 0 {
+                    entry.remove_component::<Contamination>();
+                    finished.push(*squad);
+                }
+            }
+        }
+    }
+    for (_, slime, amount) in cleaned.iter() {
+        // Без утилизации половина слизи уходит в канализацию
+        // при зачистке, что не влезло в чаны - туда же
+        let collected = if recycling { amount.0 } else { amount.0 / 2 };
+        let _ = put_resource(world, *slime, RealUnits(collected));
+    }
+    // Отработавшие отряды возвращаются в казарму
+    for squad in finished.iter() {
+        if let Some(mut entry) = world.entry(*squad) {
+            entry.remove_component::<DispatchedTo>();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::production::spawn_ready_germ;
+
+    #[test]
+    fn test_squad_needs_department_and_barracks() {
+        let mut world = World::default();
+        let barracks = spawn_ready_germ(&mut world, Tier::T1, AreaType::Military).unwrap();
+        let cell = spawn_ready_germ(&mut world, Tier::T1, AreaType::Living).unwrap();
+        assert_eq!(
+            form_squad(&mut world, MilitaryDep::None, barracks),
+            Err(SamosborError::NotADepartment(MilitaryDep::None)),
+        );
+        assert_eq!(
+            form_squad(&mut world, MilitaryDep::OLPS, cell),
+            Err(SamosborError::WrongAreaType {
+                room: cell,
+                expected: AreaType::Military,
+                actual: AreaType::Living,
+            }),
+        );
+        assert!(form_squad(&mut world, MilitaryDep::OLPS, barracks).is_ok());
+    }
+
+    #[test]
+    fn test_strikers_dont_fight() {
+        let mut world = World::default();
+        let barracks = spawn_ready_germ(&mut world, Tier::T1, AreaType::Military).unwrap();
+        let squad = form_squad(&mut world, MilitaryDep::OLPS, barracks).unwrap();
+        let mut soldiers = Vec::new();
+        for _ in 0..2 {
+            let soldier = spawn_comrad(&mut world, Profession::Likvidator, Tier::T1, MilitaryDep::OLPS, SciSpec::None, barracks).unwrap();
+            reinforce_squad(&mut world, squad, soldier, SquadRole::Rake).unwrap();
+            soldiers.push(soldier);
+        }
+        let full = squad_strength(&world)[&squad];
+        if let Some(mut entry) = world.entry(soldiers[0]) {
+            entry.add_component(OnStrike());
+        }
+        assert_eq!(squad_strength(&world)[&squad], full / 2);
+    }
+}
@@ -0,0 +1,280 @@
+use std::collections::HashMap;
+
+use legion::*;
+use serde::{Deserialize, Serialize};
+
+use crate::core::*;
+use crate::people::*;
+use crate::production::BuildPower;
+use crate::resources::*;
+use crate::storage::*;
+use crate::expedition::OnExpedition;
+
+/// Сколько сытости уходит за ход
+pub const HUNGER_PER_TURN: u16 = 10;
+
+/// Ниже этого - голод, минус настроение
+pub const HUNGRY_SATIETY: u16 = 100;
+
+/// Выше этого концентрат усваивается хуже
+pub const WELL_FED_SATIETY: u16 = 190;
+
+/// Выше этого - переедание, минус настроение
+pub const OVEREATING_SATIETY: u16 = 200;
+
+/// Настроение, выше которого белый концентрат уже не радует
+const T1_MOOD_CAP: u8 = 6;
+
+/// Паек: сколько пачек в ход и какого концентрата.
+/// Если нужного концентрата нет - выдается другой,
+/// сначала попроще, потом получше.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ration {
+    pub packs: u8,
+    pub grade: Tier,
+}
+
+impl Default for Ration {
+    fn default() -> Ration {
+        Ration {
+            packs: 1,
+            grade: Tier::T1,
+        }
+    }
+}
+
+/// Политика питания колонии. Глобальный ресурс.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RationPolicy {
+    /// Пайки по профессии и тиру. Кого нет - тому паек по умолчанию.
+    pub rations: HashMap<Profession, HashMap<Tier, Ration>>,
+    pub default: Ration,
+    /// Кого кормить первым, когда на всех не хватает.
+    /// Кого нет в списке - кормят последними.
+    pub famine_order: Vec<Profession>,
+}
+
+impl Default for RationPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RationPolicy {
+    pub fn new() -> RationPolicy {
+        RationPolicy {
+            rations: HashMap::new(),
+            default: Ration::default(),
+            famine_order: vec![
+                Profession::Likvidator,
+                Profession::Worker,
+                Profession::Scientist,
+                Profession::Stalker,
+                Profession::Party,
+            ],
+        }
+    }
+
+    /// Паек для комрада этой профессии и тира
+    pub fn ration(&self, prof: Profession, tier: Tier) -> Ration {
+        self.rations
+            .get(&prof)
+            .and_then(|by_tier| by_tier.get(&tier))
+            .cloned()
+            .unwrap_or(self.default)
+    }
+
+    /// Назначить паек
+    pub fn set_ration(&mut self, prof: Profession, tier: Tier, ration: Ration) {
+        self.rations
+            .entry(prof)
+            .or_default()
+            .insert(tier, ration);
+    }
+
+    /// Очередь профессии при голоде
+    fn famine_rank(&self, prof: Profession) -> usize {
+        self.famine_order
+            .iter()
+            .position(|p| *p == prof)
+            .unwrap_or(self.famine_order.len())
+    }
+}
+
+/// Концентрат нужного сорта
+fn concentrat(grade: Tier) -> Option<Resource> {
+    match grade {
+        Tier::T1 => Some(Resource::ConcentratT1),
+        Tier::T2 => Some(Resource::ConcentratT2),
+        Tier::T3 => Some(Resource::ConcentratT3),
+        Tier::NoTier => None,
+    }
+}
+
+/// В каком порядке пробовать сорта, если нужного нет
+fn grade_fallback(grade: Tier) -> [Tier; 3] {
+    match grade {
+        Tier::T3 => [Tier::T3, Tier::T2, Tier::T1],
+        Tier::T2 => [Tier::T2, Tier::T1, Tier::T3],
+        _ => [Tier::T1, Tier::T2, Tier::T3],
+    }
+}
+
+/// Сколько сытости дает пачка концентрата.
+/// Сытому пачка дает на единицу меньше.
+fn concentrat_satiety(grade: Tier, satiety: Satiety) -> u16 {
+    let base = match grade {
+        Tier::T1 => 11,
+        Tier::T2 => 12,
+        Tier::T3 => 14,
+        Tier::NoTier => 0,
+    };
+    if satiety.0 < WELL_FED_SATIETY { base } else { base - 1 }
+}
+
+/// Как пачка концентрата влияет на настроение.
+/// Белый концентрат только не дает загрустить,
+/// черный и красный радуют.
+fn concentrat_mood(grade: Tier, mood: &mut Mood) {
+    match grade {
+        Tier::T1 => if mood.0 < T1_MOOD_CAP { mood.raise(1) },
+        Tier::T2 => mood.raise(1),
+        Tier::T3 => mood.raise(2),
+        Tier::NoTier => (),
+    }
+}
+
+/// Сколько труда выдает комрад при такой сытости.
+/// Объевшийся двигается вяло и работает на три четверти.
+pub fn satiety_buildpower(
+    satiety: Satiety,
+    bp: BuildPower,
+) -> BuildPower {
+    if satiety.0 > OVEREATING_SATIETY {
+        BuildPower(bp.0 * 3 / 4)
+    } else {
+        bp
+    }
+}
+
+/// Голод. Сытость падает каждый ход,
+/// на нуле комрад умирает.
+pub(crate) fn hunger_tick(
+    world: &mut World,
+    _resources: &mut Resources,
+) {
+    let mut died_by_hunger: Vec<Entity> = Vec::new();
+    let mut query = <(
+        Entity,
+        &mut Satiety,
+        &mut Mood,
+    )>::query();
+    for (entity, sat, mood) in query.iter_mut(world) {
+        sat.0 = sat.0.saturating_sub(HUNGER_PER_TURN);
+        if sat.0 == 0 {
+            died_by_hunger.push(*entity);
+        }
+        // ниже ста - голод - минус настроение
+        if sat.0 < HUNGRY_SATIETY {
+            mood.lower(1);
+        }
+    }
+    for e in died_by_hunger.iter() {
+        world.remove(*e);
+    }
+}
+
+/// Люди едят концентрат по пайкам из `RationPolicy`.
+/// Когда на всех не хватает - кормят по очереди голода,
+/// внутри профессии сначала старшие тиры.
+pub(crate) fn consume_concentrat(
+    world: &mut World,
+    resources: &mut Resources,
+) {
+    let policy = resources
+        .get::<RationPolicy>()
+        .unwrap();
+    // сколько есть на складе
+    let mut stock: HashMap<Tier, usize> = [Tier::T1, Tier::T2, Tier::T3]
+        .iter()
+        .filter_map(|grade| concentrat(*grade).map(|res| (*grade, res)))
+        .map(|(grade, res)| (grade, how_much_we_have(world, res).0))
+        .collect();
+    // Сколько выдано
+    let mut eaten: HashMap<Tier, usize> = HashMap::new();
+
+    // Ушедшие в вылазку едят свой паек.
+    // Дети едят как тунеядцы без тира.
+    let mut query = <(
+        Entity,
+        Option<&Profession>,
+        Option<&Tier>,
+    )>::query()
+        .filter(component::<Satiety>() & !component::<OnExpedition>());
+    let mut eaters: Vec<(Entity, Profession, Tier)> = query
+        .iter(world)
+        .map(|(e, prof, tier)| (
+            *e,
+            prof.cloned().unwrap_or(Profession::NoProf),
+            tier.cloned().unwrap_or(Tier::NoTier),
+        ))
+        .collect();
+    eaters.sort_by_key(|(_, prof, tier)| (
+        policy.famine_rank(*prof),
+        match tier {
+            Tier::T3 => 0,
+            Tier::T2 => 1,
+            _ => 2,
+        },
+    ));
+
+    for (eater, prof, tier) in eaters.iter() {
+        let ration = policy.ration(*prof, *tier);
+        let mut entry = match world.entry(*eater) {
+            Some(entry) => entry,
+            None => continue,
+        };
+        let mut sat = *entry.get_component::<Satiety>().unwrap();
+        let mut mood = match entry.get_component::<Mood>() {
+            Ok(mood) => *mood,
+            Err(_) => continue,
+        };
+        let mut fed = 0;
+        for _ in 0..ration.packs {
+            let grade = grade_fallback(ration.grade)
+                .iter()
+                .cloned()
+                .find(|grade| stock.get(grade).cloned().unwrap_or(0) > 0);
+            let grade = match grade {
+                Some(grade) => grade,
+                None => break,
+            };
+            *stock.get_mut(&grade).unwrap() -= 1;
+            *eaten.entry(grade).or_insert(0) += 1;
+            sat.0 = sat.0.saturating_add(concentrat_satiety(grade, sat));
+            concentrat_mood(grade, &mut mood);
+            fed += 1;
+        }
+        if fed == 0 && ration.packs > 0 {
+            // Не дали пожрать. Настроение
+            // от такого ухудшается.
+            mood.lower(1);
+        }
+        if sat.0 > OVEREATING_SATIETY {
+            // Переел - тяжело и лениво
+            mood.lower(1);
+        }
+        if let Ok(s) = entry.get_component_mut::<Satiety>() {
+            *s = sat;
+        }
+        if let Ok(m) = entry.get_component_mut::<Mood>() {
+            *m = mood;
+        }
+    }
+    // Выдавали не больше чем было, так что списание пройдет
+    for (grade, amount) in eaten.iter() {
+        if let Some(res) = concentrat(*grade) {
+            let _ = writeoff(world, res, RealUnits(*amount));
+        }
+    }
+}
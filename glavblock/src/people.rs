@@ -3,15 +3,18 @@ use std::hash::Hash;
 use std::collections::HashMap;
 
 use legion::*;
+use serde::{Deserialize, Serialize};
 
 use crate::core::*;
 use crate::area::*;
+use crate::production::{Stationary, StationaryStatus};
+use crate::military::*;
 
 /// Сколько места занимает человек
 pub static COMRAD_RENTED_PLACE: usize = 10;
 
 /// Какому отделу ликвидаторов принадлежит боец
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum MilitaryDep {
     None, // Не военный
     OLPS, // Отдел Ликвидации Последствий Самосбора
@@ -20,7 +23,7 @@ pub enum MilitaryDep {
 }
 
 /// К какому НИИ тяготеет яйцеголовый
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SciSpec {
     None, // Не ученый
     Samosbor, // НИИ Самосбора и Последствий. Плесень, слизь, твари, абберации, патогены и прочее. Очистка материи от влияния самосбора.
@@ -41,31 +44,137 @@ pub enum SciSpec {
 /// 1 съетая пачка концентрата добавляет 11 единиц насыщения если сытость меньше 190. Если больше 190 - 10.
 /// На 100 начинается граница голодания с дебафами настроения.
 /// На 0 голодная смерть.
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Satiety(pub u16);
 
 /// Настроение комрада.
 /// Больше 10 быть не должно. 10 - счастлив.
 /// 5, 6 - нейтрал
 /// 0 - тотально несчастлив.
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Mood(pub u8);
 
-pub fn random_sci_spec () -> SciSpec {
-    match d(1,7) {
+/// Выше этого настроение не поднимается
+pub const MAX_MOOD: u8 = 10;
+
+impl Mood {
+    /// Поднять настроение, не выше `MAX_MOOD`
+    pub fn raise(&mut self, by: u8) {
+        self.0 = std::cmp::min(self.0.saturating_add(by), MAX_MOOD);
+    }
+
+    /// Опустить настроение, не ниже нуля
+    pub fn lower(&mut self, by: u8) {
+        self.0 = self.0.saturating_sub(by);
+    }
+}
+
+/// Опыт комрада. Копится с каждой единицы вложенного в задачи труда.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Experience(pub usize);
+
+/// Комрад ранен и отлеживается. Не работает.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Injured {
+    pub turns_left: usize,
+}
+
+/// Раненые понемногу встают на ноги
+pub fn injury_tick(
+    world: &mut World,
+    _resources: &mut Resources,
+) {
+    let mut healed: Vec<Entity> = Vec::new();
+    let mut query = <(Entity, &mut Injured)>::query();
+    for (entity, injured) in query.iter_mut(world) {
+        injured.turns_left = injured.turns_left.saturating_sub(1);
+        if injured.turns_left == 0 {
+            healed.push(*entity);
+        }
+    }
+    for entity in healed.iter() {
+        if let Some(mut entry) = world.entry(*entity) {
+            entry.remove_component::<Injured>();
+        }
+    }
+}
+
+/// Сколько опыта нужно чтобы дорасти до следующего тира.
+/// None - расти некуда.
+pub fn promotion_threshold(tier: Tier) -> Option<Experience> {
+    match tier {
+        Tier::NoTier => None,
+        Tier::T1 => Some(Experience(200)),
+        Tier::T2 => Some(Experience(1000)),
+        Tier::T3 => None,
+    }
+}
+
+/// Следующий тир
+fn next_tier(tier: Tier) -> Option<Tier> {
+    match tier {
+        Tier::NoTier => None,
+        Tier::T1 => Some(Tier::T2),
+        Tier::T2 => Some(Tier::T3),
+        Tier::T3 => None,
+    }
+}
+
+/// Есть ли в колонии готовый учебный класс в готовом партийном помещении.
+/// Без него нет курсов повышения квалификации.
+pub(crate) fn have_education(world: &World) -> bool {
+    let mut query = <(&Stationary, &StationaryStatus, &BelongsToRoom)>::query();
+    query
+        .iter(world)
+        .any(|(stationary, status, BelongsToRoom(room))|
+             *stationary == Stationary::Classroom
+             && *status == StationaryStatus::Ready
+             && room_is_ready(world, *room)
+             && world
+                 .entry_ref(*room)
+                 .map(|entry| entry.get_component::<AreaType>().ok() == Some(&AreaType::Party))
+                 .unwrap_or(false))
+}
+
+/// Повысить тир набравшим опыт.
+/// До T2 дорастают на практике,
+/// на T3 без партийного образования не выйти.
+pub fn promote_comrads(
+    world: &mut World,
+    _resources: &mut Resources,
+) {
+    let education = have_education(world);
+    let mut query = <(&mut Tier, &mut Experience)>::query()
+        .filter(component::<Profession>());
+    for (tier, exp) in query.iter_mut(world) {
+        if let (Some(threshold), Some(next)) = (
+            promotion_threshold(*tier),
+            next_tier(*tier),
+        ) {
+            if *exp >= threshold && (next != Tier::T3 || education) {
+                *tier = next;
+                *exp = Experience(0);
+            }
+        }
+    }
+}
+
+/// Случайная специализация ученого. Только из тех,
+/// по которым есть технологии: НИИ Культуры и НИИ Вооружения
+/// пока исследовать нечего.
+pub fn random_sci_spec (dice: &mut Dice) -> SciSpec {
+    match d(dice, 1, 5) {
         1 => SciSpec::Samosbor,
         2 => SciSpec::Nervonet,
-        3 => SciSpec::Culture,
-        4 => SciSpec::Space,
-        5 => SciSpec::Industry,
-        6 => SciSpec::Weapon,
-        7 => SciSpec::Bio,
+        3 => SciSpec::Space,
+        4 => SciSpec::Industry,
+        5 => SciSpec::Bio,
         _ => unreachable!(),
     }
 }
 
 /// Профессия
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Profession {
     NoProf, // Нет профессии
     Stalker, // Мусорщик
@@ -77,6 +186,7 @@ pub enum Profession {
 
 impl fmt::Display for Profession {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use Profession::*;
         let name = match self {
             NoProf => "Тунеядец".to_string(),
             Stalker => "Мусорщик".to_string(),
@@ -97,7 +207,11 @@ pub fn spawn_comrad(
     mdep: MilitaryDep,
     nii: SciSpec,
     room: Entity,
-) -> Entity {
+) -> Result<Entity, SamosborError> {
+    // Комрада без тира не бывает
+    if tier == Tier::NoTier {
+        return Err(SamosborError::InvalidTier(tier))
+    }
     let entity = world.push ((
         prof,
         tier,
@@ -108,7 +222,11 @@ pub fn spawn_comrad(
         Satiety(100),
         Mood(5),
     ));
-    entity
+    // Кортеж компонентов в legion не длиннее восьми
+    if let Some(mut entry) = world.entry(entity) {
+        entry.add_component(Experience(0));
+    }
+    Ok(entity)
 }
 
 /// Сколько у нас людей по профессиям
@@ -126,68 +244,45 @@ pub fn people_by_profession(
     result
 }
 
-/// Отряд ликвидаторов ОЛПС по стандарту №1-Ж
+/// Отряд ликвидаторов ОЛПС по стандарту №1-Ж.
+/// Выходит сразу снаряженным.
 pub fn spawn_1_g (
     world: &mut World,
     room: Entity,
-) {
-    // Сержант с огнеметом
-    spawn_comrad(
-        world,
-        Profession::Likvidator,
-        Tier::T2,
-        MilitaryDep::OLPS,
-        SciSpec::None,
-        room,
-    );
-
-    // Пятеро огнеметчиков
-    for _ in 0..5 {
-        spawn_comrad(
-            world,
-            Profession::Likvidator,
-            Tier::T1,
-            MilitaryDep::OLPS,
-            SciSpec::None,
-            room,
-        );
-    };
-
-    // Четверо граблистов
-    for _ in 0..4 {
-        spawn_comrad(
-            world,
-            Profession::Likvidator,
-            Tier::T1,
-            MilitaryDep::OLPS,
-            SciSpec::None,
-            room,
-        );
-    };
-
-    // Наряд с искрой
-    for _ in 0..2 {
-        spawn_comrad(
-            world,
-            Profession::Likvidator,
-            Tier::T1,
-            MilitaryDep::OLPS,
-            SciSpec::None,
-            room,
-        );
-    };
-
-    // Двое с Гранитом
-    for _ in 0..2 {
-        spawn_comrad(
-            world,
-            Profession::Likvidator,
-            Tier::T1,
-            MilitaryDep::OLPS,
-            SciSpec::None,
-            room,
-        );
-    };
+) -> Result<Entity, SamosborError> {
+    let squad = world.push((
+        Squad { dep: MilitaryDep::OLPS },
+        BelongsToRoom(room),
+    ));
+    let roster = [
+        // Сержант с огнеметом
+        (SquadRole::Sergeant, Tier::T2, 1),
+        // Пятеро огнеметчиков
+        (SquadRole::Flamethrower, Tier::T1, 5),
+        // Четверо граблистов
+        (SquadRole::Rake, Tier::T1, 4),
+        // Наряд с искрой
+        (SquadRole::Iskra, Tier::T1, 2),
+        // Двое с Гранитом
+        (SquadRole::Granit, Tier::T1, 2),
+    ];
+    for (role, tier, count) in roster.iter() {
+        for _ in 0..*count {
+            let comrad = spawn_comrad(
+                world,
+                Profession::Likvidator,
+                *tier,
+                MilitaryDep::OLPS,
+                SciSpec::None,
+                room,
+            )?;
+            if let Some(mut entry) = world.entry(comrad) {
+                entry.add_component(SquadMember { squad, role: *role });
+                entry.add_component(Equipped());
+            }
+        }
+    }
+    Ok(squad)
 }
 
 /// Сумма всего настроения в блоке
@@ -205,11 +300,62 @@ pub fn block_mood(
 /// Насколько накормлены люди
 pub fn block_satiety(
     world: &mut World,
-) -> Satiety {
+) -> usize {
     let mut result = 0;
     let mut query = <&Satiety>::query();
     for Satiety(m) in query.iter(world) {
-        result += m
+        result += *m as usize
     };
-    Satiety(result)
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::production::*;
+
+    fn veteran(world: &mut World, room: Entity) -> Entity {
+        let comrad = spawn_comrad(world, Profession::Worker, Tier::T2, MilitaryDep::None, SciSpec::None, room).unwrap();
+        if let Some(mut entry) = world.entry(comrad) {
+            entry.add_component(Experience(1000));
+        }
+        comrad
+    }
+
+    fn tier_of(world: &World, comrad: Entity) -> Tier {
+        *world.entry_ref(comrad).unwrap().get_component::<Tier>().unwrap()
+    }
+
+    #[test]
+    fn test_no_tier_comrad_rejected() {
+        let mut world = World::default();
+        let cell = spawn_ready_germ(&mut world, Tier::T1, AreaType::Living).unwrap();
+        assert_eq!(
+            spawn_comrad(&mut world, Profession::Worker, Tier::NoTier, MilitaryDep::None, SciSpec::None, cell),
+            Err(SamosborError::InvalidTier(Tier::NoTier)),
+        );
+    }
+
+    #[test]
+    fn test_party_room_alone_is_no_school() {
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        spawn_ready_germ(&mut world, Tier::T1, AreaType::Party).unwrap();
+        let cell = spawn_ready_germ(&mut world, Tier::T1, AreaType::Living).unwrap();
+        let comrad = veteran(&mut world, cell);
+        promote_comrads(&mut world, &mut resources);
+        assert_eq!(tier_of(&world, comrad), Tier::T2);
+    }
+
+    #[test]
+    fn test_classroom_opens_t3() {
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        let school = spawn_ready_germ(&mut world, Tier::T1, AreaType::Party).unwrap();
+        spawn_ready_stationary(&mut world, Stationary::Classroom, school);
+        let cell = spawn_ready_germ(&mut world, Tier::T1, AreaType::Living).unwrap();
+        let comrad = veteran(&mut world, cell);
+        promote_comrads(&mut world, &mut resources);
+        assert_eq!(tier_of(&world, comrad), Tier::T3);
+    }
 }
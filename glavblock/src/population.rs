@@ -0,0 +1,383 @@
+use std::collections::HashMap;
+
+use legion::*;
+use serde::{Deserialize, Serialize};
+
+use crate::core::*;
+use crate::area::*;
+use crate::people::*;
+use crate::production::*;
+use crate::samosbor::Mutated;
+use crate::housing::settle_or_evict;
+
+/// Сколько места в жилячейке занимает ребенок
+pub const CHILD_RENTED_PLACE: usize = 5;
+
+/// Через сколько ходов ребенок становится взрослым
+pub const ADULT_AGE: usize = 120;
+
+/// Шанс (в процентах) рождения в жилячейке за ход
+const BIRTH_CHANCE: usize = 2;
+
+/// С какого настроения взрослые в ячейке думают о детях
+const BIRTH_MOOD: u8 = 5;
+
+/// Шанс (в процентах) что к гермам за ход прибьются беженцы
+const REFUGEE_CHANCE: usize = 3;
+
+/// Сколько ходов беженцы ждут у гермы решения
+const REFUGEE_PATIENCE: usize = 5;
+
+/// Ребенок. Не работает, ест, растет.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Child {
+    pub age: usize,
+}
+
+/// Беженцы у гермы. Ждут, пока их примут или прогонят.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RefugeeGroup {
+    pub members: Vec<(Profession, Tier)>,
+    pub turns_left: usize,
+}
+
+/// Что случилось с населением за ход
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PopulationReport {
+    pub born: usize,
+    pub grown_up: usize,
+    pub refugees_arrived: usize,
+    pub refugees_left: usize,
+}
+
+/// Кем станет выросший или пришлый комрад
+fn random_profession(dice: &mut Dice) -> Profession {
+    match d(dice, 1, 20) {
+        1..=10 => Profession::Worker,
+        11..=13 => Profession::Stalker,
+        14..=16 => Profession::Likvidator,
+        17..=19 => Profession::Scientist,
+        _ => Profession::Party,
+    }
+}
+
+/// Отдел и НИИ под профессию
+fn profession_placement(
+    prof: Profession,
+    dice: &mut Dice,
+) -> (MilitaryDep, SciSpec) {
+    match prof {
+        Profession::Likvidator => (MilitaryDep::OLPS, SciSpec::None),
+        Profession::Scientist => (MilitaryDep::None, random_sci_spec(dice)),
+        _ => (MilitaryDep::None, SciSpec::None),
+    }
+}
+
+/// Родить ребенка в ячейке
+pub fn spawn_child(
+    world: &mut World,
+    room: Entity,
+) -> Entity {
+    world.push((
+        Child { age: 0 },
+        BelongsToRoom(room),
+        AreaOccupied(CHILD_RENTED_PLACE),
+        Satiety(100),
+        Mood(5),
+    ))
+}
+
+/// Рождения в жилячейках, где есть место, хотя бы двое
+/// взрослых и у взрослых нормальное настроение
+fn births(
+    world: &mut World,
+    dice: &mut Dice,
+) -> usize {
+    let mut adults: HashMap<Entity, (usize, usize)> = HashMap::new();
+    let mut adults_q = <(&BelongsToRoom, &Mood)>::query()
+        .filter(component::<Profession>() & !component::<Mutated>());
+    for (BelongsToRoom(room), mood) in adults_q.iter(world) {
+        let (count, mood_sum) = adults.entry(*room).or_insert((0, 0));
+        *count += 1;
+        *mood_sum += mood.0 as usize;
+    }
+    let mut cells_q = <(Entity, &AreaType, &StationaryStatus)>::query();
+    let cells: Vec<Entity> = cells_q
+        .iter(world)
+        .filter(|(_, area, status)|
+                **area == AreaType::Living
+                && **status == StationaryStatus::Ready)
+        .map(|(e, _, _)| *e)
+        .collect();
+    let mut born = 0;
+    for cell in cells.iter() {
+        let (count, mood_sum) = adults.get(cell).cloned().unwrap_or((0, 0));
+        if count < 2 || mood_sum / count < BIRTH_MOOD as usize {
+            continue
+        }
+        let free = get_room_free_space(world, *cell).unwrap_or(0);
+        if free < CHILD_RENTED_PLACE as i32 {
+            continue
+        }
+        if d(dice, 1, 100) <= BIRTH_CHANCE {
+            spawn_child(world, *cell);
+            born += 1;
+        }
+    }
+    born
+}
+
+/// Дети растут. Выросшие получают профессию:
+/// если есть учебный класс - по распределению,
+/// без образования все идут на производство.
+/// Взрослому нужно больше места: кто не влез в родную ячейку,
+/// переселяется. Ликвидаторы уходят в казарму, в отряд
+/// их определяют через `reinforce_squad`.
+fn grow_up(
+    world: &mut World,
+    dice: &mut Dice,
+) -> usize {
+    let mut children_q = <(Entity, &mut Child)>::query();
+    let mut adults: Vec<Entity> = Vec::new();
+    for (entity, child) in children_q.iter_mut(world) {
+        child.age += 1;
+        if child.age >= ADULT_AGE {
+            adults.push(*entity);
+        }
+    }
+    let education = have_education(world);
+    for entity in adults.iter() {
+        let prof = if education {
+            random_profession(dice)
+        } else {
+            Profession::Worker
+        };
+        let (mdep, nii) = profession_placement(prof, dice);
+        if let Some(mut entry) = world.entry(*entity) {
+            entry.remove_component::<Child>();
+            entry.add_component(prof);
+            entry.add_component(Tier::T1);
+            entry.add_component(mdep);
+            entry.add_component(nii);
+            entry.add_component(Experience(0));
+            entry.add_component(AreaOccupied(COMRAD_RENTED_PLACE));
+        }
+        let fits = world
+            .entry_ref(*entity)
+            .ok()
+            .and_then(|entry| entry.get_component::<BelongsToRoom>().ok().cloned())
+            .map(|BelongsToRoom(room)| get_room_free_space(world, room).unwrap_or(-1) >= 0)
+            .unwrap_or(false);
+        if !fits || prof == Profession::Likvidator {
+            settle_or_evict(world, *entity);
+        }
+    }
+    adults.len()
+}
+
+/// Беженцы приходят к гермам и уходят, если их долго не пускают
+fn refugees(
+    world: &mut World,
+    dice: &mut Dice,
+    report: &mut PopulationReport,
+) {
+    let mut groups_q = <(Entity, &mut RefugeeGroup)>::query();
+    let mut gone: Vec<Entity> = Vec::new();
+    for (entity, group) in groups_q.iter_mut(world) {
+        group.turns_left = group.turns_left.saturating_sub(1);
+        if group.turns_left == 0 {
+            gone.push(*entity);
+        }
+    }
+    for group in gone.iter() {
+        world.remove(*group);
+    }
+    report.refugees_left = gone.len();
+
+    if d(dice, 1, 100) <= REFUGEE_CHANCE {
+        let size = d(dice, 1, 4);
+        let members: Vec<(Profession, Tier)> = (0..size)
+            .map(|_| {
+                let tier = if d(dice, 1, 6) == 6 { Tier::T2 } else { Tier::T1 };
+                (random_profession(dice), tier)
+            })
+            .collect();
+        world.push((
+            RefugeeGroup {
+                members,
+                turns_left: REFUGEE_PATIENCE,
+            },
+        ));
+        report.refugees_arrived = size;
+    }
+}
+
+/// Ход населения: рождения, взросление, беженцы
+pub fn population_tick(
+    world: &mut World,
+    resources: &mut Resources,
+) -> PopulationReport {
+    let mut report = PopulationReport::default();
+    let mut dice = resources
+        .get_mut::<Dice>()
+        .unwrap();
+    report.born = births(world, &mut dice);
+    report.grown_up = grow_up(world, &mut dice);
+    refugees(world, &mut dice, &mut report);
+    report
+}
+
+/// Беженцы, ждущие у гермы
+pub fn pending_refugees(
+    world: &mut World,
+) -> Vec<(Entity, RefugeeGroup)> {
+    let mut query = <(Entity, &RefugeeGroup)>::query();
+    query
+        .iter(world)
+        .map(|(e, group)| (*e, group.clone()))
+        .collect()
+}
+
+/// Достать группу беженцев
+fn refugee_group(
+    world: &World,
+    group: Entity,
+) -> Result<RefugeeGroup, SamosborError> {
+    world
+        .entry_ref(group)
+        .map_err(|_| SamosborError::NoSuchEntity(group))?
+        .get_component::<RefugeeGroup>()
+        .cloned()
+        .map_err(|_| SamosborError::NoSuchEntity(group))
+}
+
+/// Пустить беженцев. Каждого селят в жилячейку со свободным местом,
+/// если места нет всем - не пускают никого.
+/// Приходят голодные и напуганные.
+pub fn accept_refugees(
+    world: &mut World,
+    resources: &mut Resources,
+    group: Entity,
+) -> Result<Vec<Entity>, SamosborError> {
+    let refugees = refugee_group(world, group)?;
+    let required = AreaOccupied(COMRAD_RENTED_PLACE * refugees.members.len());
+    let total_free: usize = {
+        let mut cells_q = <(Entity, &AreaType, &StationaryStatus)>::query();
+        let cells: Vec<Entity> = cells_q
+            .iter(world)
+            .filter(|(_, area, status)|
+                    **area == AreaType::Living
+                    && **status == StationaryStatus::Ready)
+            .map(|(e, _, _)| *e)
+            .collect();
+        cells
+            .iter()
+            .map(|cell| get_room_free_space(world, *cell).unwrap_or(0))
+            .map(|free| (free.max(0) as usize / COMRAD_RENTED_PLACE) * COMRAD_RENTED_PLACE)
+            .sum()
+    };
+    if total_free < required.0 {
+        return Err(SamosborError::NoEmptyArea {
+            area_type: AreaType::Living,
+            required,
+        })
+    }
+    let mut dice = resources
+        .get_mut::<Dice>()
+        .unwrap();
+    let mut accepted: Vec<Entity> = Vec::new();
+    for (prof, tier) in refugees.members.iter() {
+        let cell = get_sufficent_room(
+            world,
+            AreaOccupied(COMRAD_RENTED_PLACE),
+            AreaType::Living,
+        ).ok_or(SamosborError::NoEmptyArea {
+            area_type: AreaType::Living,
+            required: AreaOccupied(COMRAD_RENTED_PLACE),
+        })?;
+        let (mdep, nii) = profession_placement(*prof, &mut dice);
+        let comrad = spawn_comrad(world, *prof, *tier, mdep, nii, cell)?;
+        if let Some(mut entry) = world.entry(comrad) {
+            entry.add_component(Satiety(60));
+            entry.add_component(Mood(3));
+        }
+        accepted.push(comrad);
+    }
+    world.remove(group);
+    Ok(accepted)
+}
+
+/// Прогнать беженцев
+pub fn reject_refugees(
+    world: &mut World,
+    group: Entity,
+) -> Result<(), SamosborError> {
+    refugee_group(world, group)?;
+    world.remove(group);
+    Ok(())
+}
+
+/// Сколько в колонии детей
+pub fn children(
+    world: &mut World,
+) -> usize {
+    let mut query = <&Child>::query();
+    query.iter(world).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::housing::Homeless;
+
+    fn family(world: &mut World) -> (Entity, Entity) {
+        let cell = spawn_ready_germ(world, Tier::T1, AreaType::Living).unwrap();
+        for _ in 0..2 {
+            spawn_comrad(world, Profession::Worker, Tier::T1, MilitaryDep::None, SciSpec::None, cell).unwrap();
+        }
+        spawn_child(world, cell);
+        let teen = spawn_child(world, cell);
+        if let Some(mut entry) = world.entry(teen) {
+            entry.add_component(Child { age: ADULT_AGE - 1 });
+        }
+        (cell, teen)
+    }
+
+    fn home_of(world: &World, comrad: Entity) -> Option<Entity> {
+        world
+            .entry_ref(comrad)
+            .unwrap()
+            .get_component::<BelongsToRoom>()
+            .ok()
+            .map(|BelongsToRoom(room)| *room)
+    }
+
+    #[test]
+    fn test_grown_up_moves_out_of_full_cell() {
+        let mut world = World::default();
+        let (cell, teen) = family(&mut world);
+        let other = spawn_ready_germ(&mut world, Tier::T1, AreaType::Living).unwrap();
+        assert_eq!(grow_up(&mut world, &mut Dice::new(0)), 1);
+        assert_eq!(home_of(&world, teen), Some(other));
+        assert!(get_room_free_space(&mut world, cell).unwrap() >= 0);
+    }
+
+    #[test]
+    fn test_grown_up_homeless_without_room() {
+        let mut world = World::default();
+        let (_, teen) = family(&mut world);
+        grow_up(&mut world, &mut Dice::new(0));
+        assert_eq!(home_of(&world, teen), None);
+        assert!(world.entry_ref(teen).unwrap().get_component::<Homeless>().is_ok());
+    }
+
+    #[test]
+    fn test_block_satiety_doesnt_overflow() {
+        let mut world = World::default();
+        let cell = spawn_ready_germ(&mut world, Tier::T3, AreaType::Living).unwrap();
+        for _ in 0..700 {
+            spawn_child(&mut world, cell);
+        }
+        assert_eq!(block_satiety(&mut world), 70000);
+    }
+}
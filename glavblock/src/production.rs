@@ -1,4 +1,5 @@
 use legion::*;
+use serde::{Deserialize, Serialize};
 use std::ops::*;
 use std::hash::Hash;
 use crate::core::*;
@@ -6,19 +7,22 @@ use crate::area::*;
 use crate::people::*;
 use crate::resources::*;
 use crate::storage::*;
+use crate::definitions::definitions;
+use crate::samosbor::Mutated;
+use crate::expedition::OnExpedition;
+use crate::unrest::OnStrike;
+use crate::science::*;
+use crate::maintenance::{Durability, MAX_DURABILITY};
+use crate::utilities::disconnect_node;
 
 use std::collections::HashMap;
 
-/// Приоритет задачи
-#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
-pub struct Priority(pub usize);
-
 /// Метка того, к какому стационарному объекту принадлежит эта штука
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct BelongsToStationary (pub Entity);
 
 /// Стационарные объекты
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Stationary {
     None, // Отсутствие постройки. Заглушка для обозначения того,
     // что некоторые производственные задачи не требуют
@@ -34,23 +38,57 @@ pub enum Stationary {
     LabT3, // Абстрактное научное оборудование. Супер крутое.
     Barrel, // Чаны, в которых проходят химические реакции или хранятся текучие ресурсы. Используются в комбинации с хим, биолабораторией или печью. Забирают некое сырье, некий реагент и через какое-то время отдают другое сырье или продукт.
     Rack, // Стеллаж. Ставится в складские помещения. Увеличивает вместимость последних.
+    Classroom, // Учебный класс. Ставится в партийные помещения. Без него нет ни распределения по профессиям, ни выхода на T3.
 
     // Инфраструктура
     NeuroTerminal, // Терминал для связи с нейронетом. ЭВМ.
+    Generator, // Дизель-генератор. Узел электросети на несколько помещений.
+    WaterPump, // Насос. Узел водопровода.
+    AirPump, // Вентиляционная установка. Узел вентиляции.
 }
 
+/// Все виды стационарок
+pub const STATIONARIES: [Stationary; 15] = [
+    Stationary::None,
+    Stationary::BenchToolT1,
+    Stationary::BenchToolT2,
+    Stationary::BenchToolT3,
+    Stationary::FormatFurnace,
+    Stationary::LabT1,
+    Stationary::LabT2,
+    Stationary::LabT3,
+    Stationary::Barrel,
+    Stationary::Rack,
+    Stationary::Classroom,
+    Stationary::NeuroTerminal,
+    Stationary::Generator,
+    Stationary::WaterPump,
+    Stationary::AirPump,
+];
+
 /// Гермкомплект. Инфраструктура конкертного помещения. Бывает T1, T2, T3.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Germ ();
 
 /// В каком состоянии строение
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum StationaryStatus {
     Constructing, // Строится
     Ready, // Готово
+    Broken, // Сломано, ждет ремонта
+    UnderRepair, // Ремонтируется
+    Dismantling, // Разбирается или переносится
 }
 
+/// В каком статусе была стационарка до ремонта или разборки.
+/// В него она вернется, если работу отменят,
+/// а перенесенная - и на новом месте.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResumeStatus (pub StationaryStatus);
+
 /// Сколько единиц площади занимает стационарный объект
-pub fn stationary_size (
+/// Встроенное значение, используется если нет файла определений.
+pub(crate) fn builtin_stationary_size (
     stationary: Stationary,
 ) -> AreaOccupied {
     match stationary  {
@@ -64,40 +102,100 @@ pub fn stationary_size (
         Stationary::LabT3 => AreaOccupied(60),
         Stationary::Barrel => AreaOccupied(15),
         Stationary::Rack => AreaOccupied(5),
+        Stationary::Classroom => AreaOccupied(20),
         Stationary::NeuroTerminal => AreaOccupied(5),
+        Stationary::Generator => AreaOccupied(30),
+        Stationary::WaterPump => AreaOccupied(15),
+        Stationary::AirPump => AreaOccupied(15),
+    }
+}
+
+/// Сколько единиц площади занимает стационарный объект
+pub fn stationary_size (
+    stationary: Stationary,
+) -> AreaOccupied {
+    definitions().stationary(stationary).size
+}
+
+/// В помещениях какого назначения ставится стационарка.
+/// Первое - основное. Чаны служат и складом для жидкостей.
+pub fn stationary_areas (
+    stationary: Stationary,
+) -> &'static [AreaType] {
+    match stationary {
+        Stationary::LabT1
+            | Stationary::LabT2
+            | Stationary::LabT3 => &[AreaType::Science],
+        Stationary::Rack | Stationary::Classroom => &[AreaType::Party],
+        Stationary::Barrel => &[AreaType::Industrial, AreaType::Party],
+        _ => &[AreaType::Industrial],
     }
 }
 
-/// Поставить герму + обустроить помещение
-/// Версия для типа World
+/// Поставить герму + обустроить помещение.
+/// Герма ставится в статусе "строится", работа по ней
+/// раскладывается на задачи как у любой стационарки.
+/// Помещением можно пользоваться когда герма готова.
 pub fn install_germ(
     world: &mut World,
     tier: Tier,
     purpose: AreaType,
-) -> Entity {
-    world.push((
+    priority: TaskPriority,
+) -> Result<Entity, SamosborError> {
+    if tier == Tier::NoTier {
+        return Err(SamosborError::InvalidTier(tier))
+    }
+    require_tech(world, germ_tech(tier))?;
+    let germ = world.push((
         Germ(),
-        tier.clone(),
+        tier,
         StationaryStatus::Constructing,
-        germ_requirements(tier),
         purpose,
         tier2germ_capacity(tier),
-    ))
+    ));
+    for task_meta in germ_requirements(tier).iter() {
+        push_task(world, BelongsToStationary(germ), *task_meta, priority);
+    };
+    Ok(germ)
+}
+
+/// Поставить уже готовую герму. Для стартовой колонии и событий.
+pub fn spawn_ready_germ(
+    world: &mut World,
+    tier: Tier,
+    purpose: AreaType,
+) -> Result<Entity, SamosborError> {
+    if tier == Tier::NoTier {
+        return Err(SamosborError::InvalidTier(tier))
+    }
+    Ok(world.push((
+        Germ(),
+        tier,
+        StationaryStatus::Ready,
+        purpose,
+        tier2germ_capacity(tier),
+    )))
 }
 
 /// Вместимость гермы
-fn tier2germ_capacity(tier: Tier) -> AreaCapacity {
+/// Встроенное значение, используется если нет файла определений.
+pub(crate) fn builtin_germ_capacity(tier: Tier) -> AreaCapacity {
     match tier {
-        Tier::NoTier => unimplemented!(),
+        Tier::NoTier => AreaCapacity(0),
         Tier::T1 => AreaCapacity(30),
         Tier::T2 => AreaCapacity(150),
         Tier::T3 => AreaCapacity(500),
     }
 }
 
+/// Вместимость гермы
+fn tier2germ_capacity(tier: Tier) -> AreaCapacity {
+    definitions().germ(tier).capacity
+}
+
 /// Количество труда, которое должен затратить (затратил)
 /// работник на выполнение задачи за одну смену
-#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct BuildPower(pub usize);
 
 impl AddAssign for BuildPower {
@@ -115,7 +213,8 @@ impl SubAssign for BuildPower {
 /// Эффективность камрада
 pub fn tier2comrad_buildpower (tier: Tier) -> BuildPower {
     match tier {
-        Tier::NoTier => unreachable!(),
+        // Без тира не работник
+        Tier::NoTier => BuildPower(0),
         Tier::T1 => BuildPower(10),
         Tier::T2 => BuildPower(20),
         Tier::T3 => BuildPower(40),
@@ -143,8 +242,29 @@ pub fn buildpower_downgrage_coef(
     }
 }
 
+/// Может ли стационарка делать работу, под которую нужна `required`.
+/// Оборудование старшего тира тянет работу младших.
+pub fn stationary_serves(
+    stationary: Stationary,
+    required: Stationary,
+) -> bool {
+    if stationary == required {
+        return true
+    }
+    matches!(
+        (stationary, required),
+        (Stationary::BenchToolT2, Stationary::BenchToolT1)
+            | (Stationary::BenchToolT3, Stationary::BenchToolT1)
+            | (Stationary::BenchToolT3, Stationary::BenchToolT2)
+            | (Stationary::LabT2, Stationary::LabT1)
+            | (Stationary::LabT3, Stationary::LabT1)
+            | (Stationary::LabT3, Stationary::LabT2)
+    )
+}
+
 /// Сколько работы можно произвести на данном оборудовании
-pub fn stationary_build_power(
+/// Встроенное значение, используется если нет файла определений.
+pub(crate) fn builtin_stationary_build_power(
     stationary: Stationary,
 ) -> BuildPower{
     match stationary {
@@ -158,12 +278,24 @@ pub fn stationary_build_power(
         Stationary::LabT3 => BuildPower(10),
         Stationary::Barrel => BuildPower(10),
         Stationary::Rack => BuildPower(0),
+        Stationary::Classroom => BuildPower(0),
         Stationary::NeuroTerminal => BuildPower(10),
+        Stationary::Generator => BuildPower(0),
+        Stationary::WaterPump => BuildPower(0),
+        Stationary::AirPump => BuildPower(0),
     }
 }
 
+/// Сколько работы можно произвести на данном оборудовании
+pub fn stationary_build_power(
+    stationary: Stationary,
+) -> BuildPower{
+    definitions().stationary(stationary).build_power
+}
+
 /// Что нужно по ресурсам чтобы поставить эту стационарку
-pub fn stationary_required_resources (
+/// Встроенное значение, используется если нет файла определений.
+pub(crate) fn builtin_stationary_required_resources (
     stationary: Stationary,
 ) -> HashMap<Resource, RealUnits> {
     match stationary {
@@ -195,16 +327,39 @@ pub fn stationary_required_resources (
         Stationary::Rack => [
             (Resource::ScrapT1, RealUnits (1))
         ].iter().cloned().collect(),
+        Stationary::Classroom => [
+            (Resource::PolymerT1, RealUnits (2)),
+            (Resource::ScrapT1, RealUnits (2)),
+        ].iter().cloned().collect(),
         Stationary::NeuroTerminal => [
             (Resource::ScrapT1, RealUnits (1))
         ].iter().cloned().collect(),
+        Stationary::Generator => [
+            (Resource::ScrapT1, RealUnits (10)),
+            (Resource::ComponentT1, RealUnits (2)),
+        ].iter().cloned().collect(),
+        Stationary::WaterPump => [
+            (Resource::ScrapT1, RealUnits (5)),
+            (Resource::PolymerT1, RealUnits (2)),
+        ].iter().cloned().collect(),
+        Stationary::AirPump => [
+            (Resource::ScrapT1, RealUnits (5)),
+            (Resource::PolymerT1, RealUnits (2)),
+        ].iter().cloned().collect(),
     }
 }
 
+/// Что нужно по ресурсам чтобы поставить эту стационарку
+pub fn stationary_required_resources (
+    stationary: Stationary,
+) -> HashMap<Resource, RealUnits> {
+    definitions().stationary(stationary).required_resources.clone()
+}
+
 /// Метаданные по рабочей задаче
 /// Где-то рядом с этой рабочей задачей в ECS лежит штука
 /// которая собственно делается
-#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct TaskMeta {
     pub prof: Profession,
     pub tier: Tier, // Тир исполнителя
@@ -214,11 +369,68 @@ pub struct TaskMeta {
 }
 
 /// Приоритет задачи
-#[derive(PartialEq, Eq, Clone, Copy, Debug, PartialOrd, Ord)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct TaskPriority (pub usize);
 
+/// Сколько работы было в задаче при постановке.
+/// Вместе с остатком в `TaskMeta` дает прогресс.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct TaskTotal (pub BuildPower);
+
+/// Задача на паузе. Людей на нее не ставят,
+/// сделанное не пропадает.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Paused ();
+
+/// Поставить рабочую задачу. `owner` - метка того, чья это задача:
+/// стационарки, заказа или исследования.
+pub(crate) fn push_task<O: legion::storage::Component>(
+    world: &mut World,
+    owner: O,
+    task_meta: TaskMeta,
+    priority: TaskPriority,
+) -> Entity {
+    world.push((
+        owner,
+        task_meta,
+        priority,
+        TaskTotal(task_meta.bp),
+    ))
+}
+
+/// Назначение комрада на рабочую задачу.
+/// Нет компонента - комрад свободен.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssignedTask (pub Entity);
+
+/// Сколько комрадов сейчас при деле
+pub fn busy_comrads(
+    world: &mut World,
+) -> usize {
+    let mut query = <&AssignedTask>::query()
+        .filter(component::<Profession>());
+    query.iter(world).count()
+}
+
+/// Сколько комрадов сидит без дела.
+/// Мутанты, раненые, забастовщики и ушедшие в вылазку не в счет.
+pub fn idle_comrads(
+    world: &mut World,
+) -> usize {
+    let mut query = <&Profession>::query()
+        .filter(
+            !component::<AssignedTask>()
+            & !component::<Mutated>()
+            & !component::<Injured>()
+            & !component::<OnExpedition>()
+            & !component::<OnStrike>()
+        );
+    query.iter(world).count()
+}
+
 /// Что надо по рабочим/оборудованию чтобы построить эту стационарку
-pub fn stationary_requirements(
+/// Встроенное значение, используется если нет файла определений.
+pub(crate) fn builtin_stationary_requirements(
     target: Stationary,
 ) -> Vec<TaskMeta> {
     match target {
@@ -303,6 +515,15 @@ pub fn stationary_requirements(
                 sci_spec: SciSpec::None,
             },
         ],
+        Stationary::Classroom => vec![
+            TaskMeta {
+                prof: Profession::Worker,
+                tier: Tier::T1,
+                bp: BuildPower(20),
+                stationary: Stationary::None,
+                sci_spec: SciSpec::None,
+            },
+        ],
         Stationary::NeuroTerminal => vec![
             TaskMeta {
                 prof: Profession::Worker,
@@ -312,12 +533,47 @@ pub fn stationary_requirements(
                 sci_spec: SciSpec::None,
             },
         ],
+        Stationary::Generator => vec![
+            TaskMeta {
+                prof: Profession::Worker,
+                tier: Tier::T1,
+                bp: BuildPower(30),
+                stationary: Stationary::None,
+                sci_spec: SciSpec::None,
+            },
+        ],
+        Stationary::WaterPump => vec![
+            TaskMeta {
+                prof: Profession::Worker,
+                tier: Tier::T1,
+                bp: BuildPower(20),
+                stationary: Stationary::None,
+                sci_spec: SciSpec::None,
+            },
+        ],
+        Stationary::AirPump => vec![
+            TaskMeta {
+                prof: Profession::Worker,
+                tier: Tier::T1,
+                bp: BuildPower(20),
+                stationary: Stationary::None,
+                sci_spec: SciSpec::None,
+            },
+        ],
         Stationary::None => Vec::new (),
     }
 }
 
+/// Что надо по рабочим/оборудованию чтобы построить эту стационарку
+pub fn stationary_requirements(
+    target: Stationary,
+) -> Vec<TaskMeta> {
+    definitions().stationary(target).requirements.clone()
+}
+
 /// Что надо по рабочим/оборудованию чтобы построить такую герму
-pub fn germ_requirements(
+/// Встроенное значение, используется если нет файла определений.
+pub(crate) fn builtin_germ_requirements(
     tier: Tier,
 ) -> Vec<TaskMeta> {
     match tier {
@@ -352,20 +608,99 @@ pub fn germ_requirements(
     }
 }
 
-/// Запустить постройку
+/// Что надо по рабочим/оборудованию чтобы построить такую герму
+pub fn germ_requirements(
+    tier: Tier,
+) -> Vec<TaskMeta> {
+    definitions().germ(tier).requirements.clone()
+}
+
+/// Поставить уже готовую стационарку. Для стартовой колонии и событий.
+/// Место в помещении не проверяется.
+pub fn spawn_ready_stationary(
+    world: &mut World,
+    stationary: Stationary,
+    room: Entity,
+) -> Entity {
+    let entity = world.push((
+        stationary,
+        stationary_size(stationary),
+        StationaryStatus::Ready,
+        BelongsToRoom(room),
+        Durability(MAX_DURABILITY),
+    ));
+    spawn_stationary_containers(world, entity);
+    entity
+}
+
+/// Разобрать стационарку прямо сейчас.
+/// Содержимое мест хранения переезжает на другие склады,
+/// площадь помещения освобождается, помещения от узла отключаются.
+pub fn dismantle_stationary(
+    world: &mut World,
+    stationary: Entity,
+) -> Result<(), SamosborError> {
+    match world.entry_ref(stationary) {
+        Ok(entry) => {
+            entry
+                .get_component::<Stationary>()
+                .map_err(|_| SamosborError::NoSuchEntity(stationary))?;
+        },
+        Err(_) => return Err(SamosborError::NoSuchEntity(stationary)),
+    }
+    release_stationary_containers(world, stationary);
+    // Задачи по стационарке, если она еще строилась
+    let mut tasks_q = <(Entity, &BelongsToStationary)>::query()
+        .filter(component::<TaskMeta>());
+    let tasks: Vec<Entity> = tasks_q
+        .iter(world)
+        .filter(|(_, BelongsToStationary(s))| *s == stationary)
+        .map(|(e, _)| *e)
+        .collect();
+    for task in tasks.iter() {
+        world.remove(*task);
+    }
+    disconnect_node(world, stationary);
+    world.remove(stationary);
+    Ok(())
+}
+
+/// Запустить постройку.
+/// Возвращает строящуюся стационарку.
 pub fn start_build_task (
     world: &mut World,
     stationary: Stationary,
     room: Entity,
     priority: TaskPriority,
-) -> Result<(), SamosborError> {
-    let free_space = get_room_free_space(world, room);
+) -> Result<Entity, SamosborError> {
+    require_tech(world, stationary_tech(stationary))?;
+    let free_space = get_room_free_space(world, room)?;
+    if !room_is_ready(world, room) {
+        return Err(SamosborError::RoomNotReady(room))
+    }
+    let area = *world
+        .entry_ref(room)
+        .map_err(|_| SamosborError::NoSuchEntity(room))?
+        .get_component::<AreaType>()
+        .map_err(|_| SamosborError::NotARoom(room))?;
+    let areas = stationary_areas(stationary);
+    if !areas.contains(&area) {
+        return Err(SamosborError::WrongAreaType {
+            room,
+            expected: areas[0],
+            actual: area,
+        })
+    }
     let required_space = stationary_size(stationary);
     if free_space < required_space.0 as i32 {
-        Err(SamosborError::NotEnoughArea)
+        Err(SamosborError::NotEnoughArea {
+            room,
+            required: required_space,
+            free: free_space,
+        })
     } else {
         let required_resources = stationary_required_resources(stationary);
-        let _ = writeoff_bunch(world, required_resources)?;
+        writeoff_bunch(world, required_resources)?;
         let task_id = world.push((
             stationary,
             stationary_size(stationary),
@@ -374,12 +709,58 @@ pub fn start_build_task (
         ));
         let requirements = stationary_requirements(stationary);
         for task_meta in requirements.iter() {
-            world.push((
-                BelongsToStationary(task_id),
-                task_meta.clone(),
-                priority,
-            ));
+            push_task(world, BelongsToStationary(task_id), *task_meta, priority);
         };
-        Ok (())
+        Ok (task_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colony::*;
+    use crate::turn::turn;
+
+    #[test]
+    fn test_no_tier_germ_rejected() {
+        let mut world = World::default();
+        assert_eq!(
+            install_germ(&mut world, Tier::NoTier, AreaType::Living, TaskPriority(0)),
+            Err(SamosborError::InvalidTier(Tier::NoTier)),
+        );
+        assert_eq!(
+            spawn_ready_germ(&mut world, Tier::NoTier, AreaType::Living),
+            Err(SamosborError::InvalidTier(Tier::NoTier)),
+        );
+    }
+
+    #[test]
+    fn test_germ_usable_once_built() {
+        let mut world = World::default();
+        let mut resources = init_resources(0);
+        let cell = spawn_ready_germ(&mut world, Tier::T1, AreaType::Living).unwrap();
+        spawn_comrad(&mut world, Profession::Worker, Tier::T1, MilitaryDep::None, SciSpec::None, cell).unwrap();
+        let germ = install_germ(&mut world, Tier::T1, AreaType::Science, TaskPriority(0)).unwrap();
+        assert!(!room_is_ready(&world, germ));
+        assert_eq!(get_sufficent_room(&mut world, AreaOccupied(1), AreaType::Science), None);
+        for _ in 0..5 {
+            turn(&mut world, &mut resources);
+        }
+        assert!(room_is_ready(&world, germ));
+        assert_eq!(get_sufficent_room(&mut world, AreaOccupied(1), AreaType::Science), Some(germ));
+    }
+
+    #[test]
+    fn test_stationary_needs_its_area() {
+        let mut world = World::default();
+        let cell = spawn_ready_germ(&mut world, Tier::T1, AreaType::Living).unwrap();
+        assert_eq!(
+            start_build_task(&mut world, Stationary::FormatFurnace, cell, TaskPriority(0)),
+            Err(SamosborError::WrongAreaType {
+                room: cell,
+                expected: AreaType::Industrial,
+                actual: AreaType::Living,
+            }),
+        );
     }
 }
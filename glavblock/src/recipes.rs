@@ -0,0 +1,298 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use legion::*;
+use serde::{Deserialize, Serialize};
+
+use crate::core::*;
+use crate::people::*;
+use crate::production::*;
+use crate::resources::*;
+use crate::storage::*;
+use crate::science::*;
+
+/// Рецепты переработки одних ресурсов в другие
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Recipe {
+    MechanicalComponent, // Механический компонент из чермета. На верстаке.
+    ElectronicComponent, // Электронный компонент из цветмета и пластика. На T3 верстаке.
+    ConcentratT1, // Белый концентрат из загрязненного биосырья. В печи.
+    ConcentratT2, // Черный концентрат из биосырья. В печи.
+    ConcentratT3, // Красный концентрат из очищенного биосырья. В печи.
+    BioRawCleaning, // Очистка загрязненного биосырья экоцидом. В чанах.
+    BioRawRefining, // Доочистка биосырья компониумом. В чанах.
+    Vulcanization, // Пластик из синтетической ткани. В печи.
+}
+
+/// Метка того, к какому производственному заказу относится задача
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BelongsToOrder (pub Entity);
+
+/// Что уходит в переработку за одну партию
+pub fn recipe_inputs(
+    recipe: Recipe,
+) -> HashMap<Resource, RealUnits> {
+    match recipe {
+        Recipe::MechanicalComponent => [
+            (Resource::ScrapT1, RealUnits (2)),
+        ].iter().cloned().collect(),
+        Recipe::ElectronicComponent => [
+            (Resource::ScrapT2, RealUnits (2)),
+            (Resource::PolymerT2, RealUnits (1)),
+        ].iter().cloned().collect(),
+        Recipe::ConcentratT1 => [
+            (Resource::BioRawT1, RealUnits (1)),
+        ].iter().cloned().collect(),
+        Recipe::ConcentratT2 => [
+            (Resource::BioRawT2, RealUnits (1)),
+        ].iter().cloned().collect(),
+        Recipe::ConcentratT3 => [
+            (Resource::BioRawT3, RealUnits (1)),
+        ].iter().cloned().collect(),
+        Recipe::BioRawCleaning => [
+            (Resource::BioRawT1, RealUnits (2)),
+            (Resource::ReagentT1, RealUnits (1)),
+        ].iter().cloned().collect(),
+        Recipe::BioRawRefining => [
+            (Resource::BioRawT2, RealUnits (2)),
+            (Resource::ReagentT2, RealUnits (1)),
+        ].iter().cloned().collect(),
+        Recipe::Vulcanization => [
+            (Resource::PolymerT1, RealUnits (5)),
+        ].iter().cloned().collect(),
+    }
+}
+
+/// Что получаем с одной партии
+pub fn recipe_outputs(
+    recipe: Recipe,
+) -> HashMap<Resource, RealUnits> {
+    match recipe {
+        Recipe::MechanicalComponent => [
+            (Resource::ComponentT1, RealUnits (1)),
+        ].iter().cloned().collect(),
+        Recipe::ElectronicComponent => [
+            (Resource::ComponentT2, RealUnits (1)),
+        ].iter().cloned().collect(),
+        Recipe::ConcentratT1 => [
+            (Resource::ConcentratT1, RealUnits (10)),
+        ].iter().cloned().collect(),
+        Recipe::ConcentratT2 => [
+            (Resource::ConcentratT2, RealUnits (10)),
+        ].iter().cloned().collect(),
+        Recipe::ConcentratT3 => [
+            (Resource::ConcentratT3, RealUnits (10)),
+        ].iter().cloned().collect(),
+        Recipe::BioRawCleaning => [
+            (Resource::BioRawT2, RealUnits (1)),
+        ].iter().cloned().collect(),
+        Recipe::BioRawRefining => [
+            (Resource::BioRawT3, RealUnits (1)),
+        ].iter().cloned().collect(),
+        Recipe::Vulcanization => [
+            (Resource::PolymerT2, RealUnits (1)),
+        ].iter().cloned().collect(),
+    }
+}
+
+/// На каком оборудовании делается
+pub fn recipe_stationary(
+    recipe: Recipe,
+) -> Stationary {
+    match recipe {
+        Recipe::MechanicalComponent => Stationary::BenchToolT1,
+        Recipe::ElectronicComponent => Stationary::BenchToolT3,
+        Recipe::ConcentratT1 => Stationary::FormatFurnace,
+        Recipe::ConcentratT2 => Stationary::FormatFurnace,
+        Recipe::ConcentratT3 => Stationary::FormatFurnace,
+        Recipe::BioRawCleaning => Stationary::Barrel,
+        Recipe::BioRawRefining => Stationary::Barrel,
+        Recipe::Vulcanization => Stationary::FormatFurnace,
+    }
+}
+
+/// Сколько и чьего труда уходит на одну партию
+pub fn recipe_requirements(
+    recipe: Recipe,
+) -> Vec<TaskMeta> {
+    let stationary = recipe_stationary(recipe);
+    match recipe {
+        Recipe::MechanicalComponent => vec![
+            TaskMeta {
+                prof: Profession::Worker,
+                tier: Tier::T1,
+                bp: BuildPower(10),
+                stationary,
+                sci_spec: SciSpec::None,
+            },
+        ],
+        Recipe::ElectronicComponent => vec![
+            TaskMeta {
+                prof: Profession::Worker,
+                tier: Tier::T2,
+                bp: BuildPower(20),
+                stationary,
+                sci_spec: SciSpec::None,
+            },
+        ],
+        Recipe::ConcentratT1 => vec![
+            TaskMeta {
+                prof: Profession::Worker,
+                tier: Tier::T1,
+                bp: BuildPower(10),
+                stationary,
+                sci_spec: SciSpec::None,
+            },
+        ],
+        Recipe::ConcentratT2 => vec![
+            TaskMeta {
+                prof: Profession::Worker,
+                tier: Tier::T2,
+                bp: BuildPower(20),
+                stationary,
+                sci_spec: SciSpec::None,
+            },
+        ],
+        Recipe::ConcentratT3 => vec![
+            TaskMeta {
+                prof: Profession::Worker,
+                tier: Tier::T3,
+                bp: BuildPower(40),
+                stationary,
+                sci_spec: SciSpec::None,
+            },
+        ],
+        Recipe::BioRawCleaning => vec![
+            TaskMeta {
+                prof: Profession::Worker,
+                tier: Tier::T1,
+                bp: BuildPower(10),
+                stationary,
+                sci_spec: SciSpec::None,
+            },
+        ],
+        Recipe::BioRawRefining => vec![
+            TaskMeta {
+                prof: Profession::Worker,
+                tier: Tier::T2,
+                bp: BuildPower(20),
+                stationary,
+                sci_spec: SciSpec::None,
+            },
+        ],
+        Recipe::Vulcanization => vec![
+            TaskMeta {
+                prof: Profession::Worker,
+                tier: Tier::T1,
+                bp: BuildPower(10),
+                stationary,
+                sci_spec: SciSpec::None,
+            },
+        ],
+    }
+}
+
+/// Есть ли в колонии введенное в эксплуатацию оборудование,
+/// на котором можно делать работу для этого типа
+pub fn have_ready_stationary(
+    world: &mut World,
+    stationary: Stationary,
+) -> bool {
+    let mut query = <(&Stationary, &StationaryStatus)>::query();
+    query
+        .iter(world)
+        .any(|(stat, status)|
+             stationary_serves(*stat, stationary)
+             && *status == StationaryStatus::Ready)
+}
+
+/// Запустить партию по рецепту.
+/// Сырье списывается сразу, продукт кладется на склад
+/// когда все задачи заказа выполнены.
+pub fn start_recipe_task(
+    world: &mut World,
+    recipe: Recipe,
+    priority: TaskPriority,
+) -> Result<Entity, SamosborError> {
+    require_tech(world, recipe_tech(recipe))?;
+    let stationary = recipe_stationary(recipe);
+    if !have_ready_stationary(world, stationary) {
+        return Err(SamosborError::NoStationary(stationary))
+    }
+    writeoff_bunch(world, recipe_inputs(recipe))?;
+    let order = world.push((recipe,));
+    for task_meta in recipe_requirements(recipe).iter() {
+        push_task(world, BelongsToOrder(order), *task_meta, priority);
+    };
+    Ok(order)
+}
+
+/// Выдать продукцию по заказам, у которых не осталось задач.
+/// Предполагается что завершенные таски удалены предыдущей системой.
+pub fn complete_production_orders(
+    world: &mut World,
+    _resources: &mut Resources,
+) {
+    let mut in_progress: HashSet<Entity> = HashSet::new();
+    let mut in_progress_q = <&BelongsToOrder>::query()
+        .filter(component::<TaskMeta>());
+    for BelongsToOrder(order) in in_progress_q.iter(world) {
+        in_progress.insert(*order);
+    };
+    let mut orders_q = <(Entity, &Recipe)>::query();
+    let completed: Vec<(Entity, Recipe)> = orders_q
+        .iter(world)
+        .filter(|(entity, _)| !in_progress.contains(entity))
+        .map(|(entity, recipe)| (*entity, *recipe))
+        .collect();
+    for (order, recipe) in completed.iter() {
+        for (res, amount) in recipe_outputs(*recipe).iter() {
+            // Что не влезло на склад - сложили кучей у гермы
+            put_or_dump(world, *res, *amount);
+        }
+        world.remove(*order);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::area::*;
+    use crate::colony::*;
+    use crate::turn::turn;
+
+    #[test]
+    fn test_mechanical_component_made_on_bench() {
+        let mut world = World::default();
+        let mut resources = init_resources(0);
+        let room = world.push(());
+        world.push((Stationary::BenchToolT1, StationaryStatus::Ready, BelongsToRoom(room)));
+        spawn_comrad(&mut world, Profession::Worker, Tier::T1, MilitaryDep::None, SciSpec::None, room).unwrap();
+        put_or_dump(&mut world, Resource::ScrapT1, RealUnits(5));
+        assert!(start_recipe_task(&mut world, Recipe::MechanicalComponent, TaskPriority(0)).is_ok());
+        assert_eq!(how_much_we_have(&mut world, Resource::ScrapT1), RealUnits(3));
+        for _ in 0..3 {
+            turn(&mut world, &mut resources);
+        }
+        assert_eq!(how_much_we_have(&mut world, Resource::ComponentT1), RealUnits(1));
+    }
+
+    #[test]
+    fn test_recipe_needs_stationary() {
+        let mut world = World::default();
+        assert!(matches!(
+            start_recipe_task(&mut world, Recipe::MechanicalComponent, TaskPriority(0)),
+            Err(SamosborError::NoStationary(Stationary::BenchToolT1)),
+        ));
+    }
+
+    #[test]
+    fn test_output_kept_without_storage() {
+        // Ни одного склада: продукция ложится кучей, но не пропадает
+        let mut world = World::default();
+        let mut resources = init_resources(0);
+        world.push((Recipe::ConcentratT1,));
+        complete_production_orders(&mut world, &mut resources);
+        assert_eq!(how_much_we_have(&mut world, Resource::ConcentratT1), RealUnits(10));
+    }
+}
@@ -1,7 +1,9 @@
 use std::fmt;
 use std::hash::Hash;
 
-#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash)]
+use serde::{Deserialize, Serialize};
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash, Serialize, Deserialize)]
 pub enum Resource {
     BioRawT1, // загрязненное биологическое сырье.
     BioRawT2, // чистое биологическое сырье.
@@ -36,16 +38,49 @@ pub enum Resource {
     ConcentratT3, // красный пищевой концентрат
 }
 
+/// Все виды ресурсов
+pub const RESOURCES: [Resource; 26] = [
+    Resource::BioRawT1,
+    Resource::BioRawT2,
+    Resource::BioRawT3,
+    Resource::ScrapT1,
+    Resource::ScrapT2,
+    Resource::ScrapT3,
+    Resource::Concrete,
+    Resource::IsoConcrente,
+    Resource::TransparentSlime,
+    Resource::BlackSlime,
+    Resource::BrownSlime,
+    Resource::RedSlime,
+    Resource::PinkSlime,
+    Resource::WhiteSlime,
+    Resource::ComponentT1,
+    Resource::ComponentT2,
+    Resource::ComponentT3,
+    Resource::ReagentT1,
+    Resource::ReagentT2,
+    Resource::ReagentT3,
+    Resource::PolymerT1,
+    Resource::PolymerT2,
+    Resource::PolymerT3,
+    Resource::ConcentratT1,
+    Resource::ConcentratT2,
+    Resource::ConcentratT3,
+];
+
 impl fmt::Display for Resource {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use Resource::*;
         let name = match self {
-            NoProf => "Тунеядец".to_string(),
             BioRawT1 => "Загрязненное биосырье".to_string(),
             BioRawT2 => "Биосырье".to_string(),
             BioRawT3 => "Очищенное биосырье".to_string(),
             ScrapT1 => "Чермет".to_string(),
             ScrapT2 => "Цветмет".to_string(),
             ScrapT3 => "Редкие металлы".to_string(),
+            Concrete => "Бетонная крошка".to_string(),
+            IsoConcrente => "Изобетон".to_string(),
+
             TransparentSlime => "Прозрачная слизь".to_string(),
             BlackSlime => "Черная слизь".to_string(),
             BrownSlime => "Коричневая слизь".to_string(),
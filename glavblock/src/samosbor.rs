@@ -0,0 +1,316 @@
+use std::collections::HashSet;
+
+use legion::*;
+use serde::{Deserialize, Serialize};
+
+use crate::core::*;
+use crate::area::*;
+use crate::people::*;
+use crate::production::*;
+use crate::resources::*;
+use crate::storage::*;
+use crate::science::*;
+use crate::expedition::OnExpedition;
+use crate::military::room_defense;
+use crate::population::Child;
+
+/// Сколько ходов после самосбора гарантированно тихо
+const SAMOSBOR_QUIET_TURNS: usize = 10;
+
+/// На сколько процентов растет шанс самосбора
+/// с каждым ходом после затишья
+const SAMOSBOR_CHANCE_STEP: usize = 2;
+
+/// Выше этого шанс самосбора не поднимается
+const SAMOSBOR_MAX_CHANCE: usize = 50;
+
+/// Сколько силы отрядов в помещении дает +1 к броску на выживание
+const DEFENSE_PER_BONUS: usize = 10;
+
+/// Больше этого отряды выживание не поднимают
+const MAX_DEFENSE_BONUS: usize = 3;
+
+/// Часовой самосбора. Глобальный ресурс.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SamosborClock {
+    pub turns_since_last: usize,
+    pub total: usize, // сколько самосборов пережила колония
+}
+
+impl Default for SamosborClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SamosborClock {
+    pub fn new() -> SamosborClock {
+        SamosborClock {
+            turns_since_last: 0,
+            total: 0,
+        }
+    }
+}
+
+/// Помещение залито слизью после самосбора.
+/// Пока отряд ОЛПС не зачистит - оборудование в нем не работает,
+/// а люди в нем теряют настроение.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Contamination {
+    pub slime: Resource,
+    pub amount: RealUnits,
+}
+
+/// Комрад попал под самосбор и изменился.
+/// Не работает.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Mutated ();
+
+/// Что случилось в помещениях за один самосбор
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SamosborReport {
+    pub sealed: usize,
+    pub breached: usize,
+    pub killed: usize,
+    pub mutated: usize,
+}
+
+/// Насколько учения поднимают шанс закрыть герму
+const SEAL_DRILLS_BONUS: usize = 10;
+
+/// Шанс (в процентах) что гермодверь успеет закрыться
+fn seal_chance(tier: Tier, drills: bool) -> usize {
+    let chance = match tier {
+        Tier::NoTier => 0,
+        Tier::T1 => 70,
+        Tier::T2 => 85,
+        Tier::T3 => 95,
+    };
+    if drills && chance > 0 {
+        // Совсем без риска самосбор не проходит
+        std::cmp::min(chance + SEAL_DRILLS_BONUS, 99)
+    } else {
+        chance
+    }
+}
+
+/// Какой слизью зальет помещение
+pub(crate) fn random_slime(dice: &mut Dice) -> Resource {
+    if d(dice, 1, 100) == 100 {
+        return Resource::WhiteSlime
+    }
+    match d(dice, 1, 5) {
+        1 => Resource::TransparentSlime,
+        2 => Resource::BlackSlime,
+        3 => Resource::BrownSlime,
+        4 => Resource::RedSlime,
+        5 => Resource::PinkSlime,
+        _ => unreachable!(),
+    }
+}
+
+/// Будет ли на этом ходу самосбор
+fn samosbor_happens(
+    clock: &SamosborClock,
+    dice: &mut Dice,
+) -> bool {
+    if clock.turns_since_last <= SAMOSBOR_QUIET_TURNS {
+        return false
+    }
+    let chance = std::cmp::min(
+        (clock.turns_since_last - SAMOSBOR_QUIET_TURNS) * SAMOSBOR_CHANCE_STEP,
+        SAMOSBOR_MAX_CHANCE,
+    );
+    d(dice, 1, 100) <= chance
+}
+
+/// Часы самосбора. Раз в сколько-то ходов случается самосбор.
+pub fn samosbor_tick(
+    world: &mut World,
+    resources: &mut Resources,
+) {
+    let happens = {
+        let mut clock = resources
+            .get_mut::<SamosborClock>()
+            .unwrap();
+        let mut dice = resources
+            .get_mut::<Dice>()
+            .unwrap();
+        clock.turns_since_last += 1;
+        samosbor_happens(&clock, &mut dice)
+    };
+    if happens {
+        samosbor(world, resources);
+    }
+}
+
+/// Самосбор. Гермы пытаются закрыться, в незакрытых
+/// помещениях люди гибнут или мутируют, все заливает слизью.
+pub fn samosbor(
+    world: &mut World,
+    resources: &mut Resources,
+) -> SamosborReport {
+    let mut report = SamosborReport::default();
+    let mut dice = resources
+        .get_mut::<Dice>()
+        .unwrap();
+    {
+        let mut clock = resources
+            .get_mut::<SamosborClock>()
+            .unwrap();
+        clock.turns_since_last = 0;
+        clock.total += 1;
+    }
+
+    // Какие гермы не успели закрыться.
+    // Недостроенная герма не закрывается вообще.
+    let drills = is_researched(world, Tech::SealDrills);
+    let mut breached: HashSet<Entity> = HashSet::new();
+    let mut germs_q = <(Entity, &Germ, &Tier, &StationaryStatus)>::query();
+    let germs: Vec<(Entity, Tier, StationaryStatus)> = germs_q
+        .iter(world)
+        .map(|(e, _, tier, status)| (*e, *tier, *status))
+        .collect();
+    for (germ, tier, status) in germs.iter() {
+        let sealed = *status == StationaryStatus::Ready
+            && d(&mut dice, 1, 100) <= seal_chance(*tier, drills);
+        if sealed {
+            report.sealed += 1;
+        } else {
+            breached.insert(*germ);
+        }
+    }
+    report.breached = breached.len();
+
+    // Люди в незакрытых помещениях.
+    // Стоящие в помещении отряды прикрывают людей от тварей.
+    let defense = room_defense(world);
+    let mut killed: Vec<Entity> = Vec::new();
+    let mut mutated: Vec<Entity> = Vec::new();
+    // Ушедшие во внешний блок переживают самосбор там
+    let mut people_q = <(Entity, &BelongsToRoom, &mut Mood)>::query()
+        .filter(
+            (component::<Profession>() | component::<Child>())
+            & !component::<OnExpedition>()
+        );
+    for (entity, BelongsToRoom(room), mood) in people_q.iter_mut(world) {
+        if !breached.contains(room) {
+            continue
+        }
+        let bonus = std::cmp::min(
+            defense.get(room).cloned().unwrap_or(0) / DEFENSE_PER_BONUS,
+            MAX_DEFENSE_BONUS,
+        );
+        match d(&mut dice, 1, 6) + bonus {
+            1 | 2 => killed.push(*entity),
+            3 => mutated.push(*entity),
+            _ => mood.lower(2), // выжил, но насмотрелся
+        }
+    }
+    for entity in killed.iter() {
+        world.remove(*entity);
+    }
+    for entity in mutated.iter() {
+        if let Some(mut entry) = world.entry(*entity) {
+            entry.add_component(Mutated());
+        }
+    }
+    report.killed = killed.len();
+    report.mutated = mutated.len();
+
+    // Слизь. Гермы обходим в порядке запроса, а не множества,
+    // чтобы броски шли в одном порядке при одном сиде.
+    for (room, _, _) in germs.iter().filter(|(germ, _, _)| breached.contains(germ)) {
+        let slime = random_slime(&mut dice);
+        let amount = RealUnits(d(&mut dice, 2, 6) * 10);
+        contaminate(world, *room, slime, amount);
+    }
+    report
+}
+
+/// Залить помещение слизью. Зачисткой займутся отряды ОЛПС.
+fn contaminate(
+    world: &mut World,
+    room: Entity,
+    slime: Resource,
+    amount: RealUnits,
+) {
+    let mut entry = match world.entry(room) {
+        Some(entry) => entry,
+        None => return,
+    };
+    let contamination = match entry.get_component::<Contamination>() {
+        // Слизь поверх слизи. Остается старая, объем растет.
+        Ok(old) => Contamination {
+            slime: old.slime,
+            amount: RealUnits(old.amount.0 + amount.0),
+        },
+        Err(_) => Contamination { slime, amount },
+    };
+    entry.add_component(contamination);
+}
+
+/// Люди в залитых помещениях теряют настроение
+pub fn contamination_tick(
+    world: &mut World,
+    _resources: &mut Resources,
+) {
+    let mut contaminated_q = <Entity>::query()
+        .filter(component::<Contamination>());
+    let contaminated: HashSet<Entity> = contaminated_q
+        .iter(world)
+        .cloned()
+        .collect();
+    let mut people_q = <(&BelongsToRoom, &mut Mood)>::query();
+    for (BelongsToRoom(room), mood) in people_q.iter_mut(world) {
+        if contaminated.contains(room) {
+            mood.lower(1);
+        }
+    }
+}
+
+/// Сколько помещений сейчас залито слизью
+pub fn contaminated_rooms(
+    world: &mut World,
+) -> usize {
+    let mut query = <&Contamination>::query();
+    query.iter(world).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colony::*;
+
+    #[test]
+    fn test_quiet_after_samosbor() {
+        let clock = SamosborClock {
+            turns_since_last: SAMOSBOR_QUIET_TURNS,
+            total: 1,
+        };
+        let mut dice = Dice::new(0);
+        for _ in 0..100 {
+            assert!(!samosbor_happens(&clock, &mut dice));
+        }
+    }
+
+    #[test]
+    fn test_unfinished_germ_breached() {
+        let mut world = World::default();
+        let mut resources = init_resources(0);
+        let room = install_germ(&mut world, Tier::T1, AreaType::Living, TaskPriority(0)).unwrap();
+        for _ in 0..20 {
+            spawn_comrad(&mut world, Profession::Worker, Tier::T1, MilitaryDep::None, SciSpec::None, room).unwrap();
+        }
+        let report = samosbor(&mut world, &mut resources);
+        assert_eq!(report.sealed, 0);
+        assert_eq!(report.breached, 1);
+        assert_eq!(contaminated_rooms(&mut world), 1);
+        assert_eq!(resources.get::<SamosborClock>().unwrap().total, 1);
+        // Погибшие убраны, мутанты и выжившие остались
+        let mut people_q = <&Profession>::query();
+        assert_eq!(people_q.iter(&world).count(), 20 - report.killed);
+        let mut mutants_q = <&Mutated>::query();
+        assert_eq!(mutants_q.iter(&world).count(), report.mutated);
+    }
+}
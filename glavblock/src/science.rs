@@ -0,0 +1,280 @@
+use std::collections::HashSet;
+
+use legion::*;
+use serde::{Deserialize, Serialize};
+
+use crate::core::*;
+use crate::people::*;
+use crate::production::*;
+use crate::recipes::Recipe;
+
+/// Технологии НИИ Главблока
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Tech {
+    Vulcanization, // Вулканизация синтетики. Открывает рецепт пластика.
+    Biorefining, // Доочистка биосырья. Открывает доочистку и красный концентрат.
+    Electronics, // Электроника. Открывает T3 верстак и электронные компоненты.
+    LabEquipment, // Приборостроение. Открывает T2 лабораторию.
+    AdvancedLabs, // Экспериментальная физика. Открывает T3 лабораторию.
+    Neuronet, // Протоколы нейронета. Открывает нейротерминал.
+    HermeticGerms, // Гермостроение. Открывает T3 гермы.
+    SealDrills, // Учения по закрытию герм. Гермы закрываются надежнее.
+    SlimeRecycling, // Утилизация слизи. При зачистке слизь не теряется.
+}
+
+/// Все технологии
+pub const TECHS: [Tech; 9] = [
+    Tech::Vulcanization,
+    Tech::Biorefining,
+    Tech::Electronics,
+    Tech::LabEquipment,
+    Tech::AdvancedLabs,
+    Tech::Neuronet,
+    Tech::HermeticGerms,
+    Tech::SealDrills,
+    Tech::SlimeRecycling,
+];
+
+/// Исследование технологии. Сущность-проект,
+/// по ней как по стройке раскладываются задачи.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ResearchStatus {
+    InProgress, // Исследуется
+    Done, // Изучено
+}
+
+/// Метка того, к какому исследованию относится задача
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BelongsToResearch (pub Entity);
+
+/// По какой специализации технология
+pub fn tech_spec(tech: Tech) -> SciSpec {
+    match tech {
+        Tech::Vulcanization => SciSpec::Industry,
+        Tech::Biorefining => SciSpec::Bio,
+        Tech::Electronics => SciSpec::Industry,
+        Tech::LabEquipment => SciSpec::Industry,
+        Tech::AdvancedLabs => SciSpec::Space,
+        Tech::Neuronet => SciSpec::Nervonet,
+        Tech::HermeticGerms => SciSpec::Samosbor,
+        Tech::SealDrills => SciSpec::Samosbor,
+        Tech::SlimeRecycling => SciSpec::Bio,
+    }
+}
+
+/// Что нужно изучить до этой технологии
+pub fn tech_prerequisites(tech: Tech) -> Vec<Tech> {
+    match tech {
+        Tech::Vulcanization => vec![],
+        Tech::Biorefining => vec![],
+        Tech::Electronics => vec![Tech::Vulcanization],
+        Tech::LabEquipment => vec![],
+        Tech::AdvancedLabs => vec![Tech::LabEquipment],
+        Tech::Neuronet => vec![Tech::Electronics, Tech::LabEquipment],
+        Tech::HermeticGerms => vec![Tech::SealDrills, Tech::LabEquipment],
+        Tech::SealDrills => vec![],
+        Tech::SlimeRecycling => vec![Tech::Biorefining],
+    }
+}
+
+/// Сколько и чьего труда уходит на исследование
+pub fn tech_requirements(tech: Tech) -> Vec<TaskMeta> {
+    let (tier, bp, stationary) = match tech {
+        Tech::Vulcanization => (Tier::T1, 100, Stationary::LabT1),
+        Tech::Biorefining => (Tier::T1, 100, Stationary::LabT1),
+        Tech::Electronics => (Tier::T2, 300, Stationary::LabT2),
+        Tech::LabEquipment => (Tier::T1, 200, Stationary::LabT1),
+        Tech::AdvancedLabs => (Tier::T2, 500, Stationary::LabT2),
+        Tech::Neuronet => (Tier::T3, 1000, Stationary::LabT3),
+        Tech::HermeticGerms => (Tier::T2, 600, Stationary::LabT2),
+        Tech::SealDrills => (Tier::T1, 150, Stationary::LabT1),
+        Tech::SlimeRecycling => (Tier::T2, 300, Stationary::LabT2),
+    };
+    vec![
+        TaskMeta {
+            prof: Profession::Scientist,
+            tier,
+            bp: BuildPower(bp),
+            stationary,
+            sci_spec: tech_spec(tech),
+        },
+    ]
+}
+
+/// Какая технология нужна под рецепт
+pub fn recipe_tech(recipe: Recipe) -> Option<Tech> {
+    match recipe {
+        Recipe::Vulcanization => Some(Tech::Vulcanization),
+        Recipe::BioRawRefining => Some(Tech::Biorefining),
+        Recipe::ConcentratT3 => Some(Tech::Biorefining),
+        Recipe::ElectronicComponent => Some(Tech::Electronics),
+        _ => None,
+    }
+}
+
+/// Какая технология нужна чтобы строить стационарку
+pub fn stationary_tech(stationary: Stationary) -> Option<Tech> {
+    match stationary {
+        Stationary::BenchToolT3 => Some(Tech::Electronics),
+        Stationary::LabT2 => Some(Tech::LabEquipment),
+        Stationary::LabT3 => Some(Tech::AdvancedLabs),
+        Stationary::NeuroTerminal => Some(Tech::Neuronet),
+        _ => None,
+    }
+}
+
+/// Какая технология нужна чтобы ставить гермы этого тира
+pub fn germ_tech(tier: Tier) -> Option<Tech> {
+    match tier {
+        Tier::T3 => Some(Tech::HermeticGerms),
+        _ => None,
+    }
+}
+
+/// Изученные технологии
+pub fn researched_techs(
+    world: &World,
+) -> HashSet<Tech> {
+    let mut query = <(&Tech, &ResearchStatus)>::query();
+    query
+        .iter(world)
+        .filter(|(_, status)| **status == ResearchStatus::Done)
+        .map(|(tech, _)| *tech)
+        .collect()
+}
+
+/// Изучена ли технология
+pub fn is_researched(
+    world: &World,
+    tech: Tech,
+) -> bool {
+    researched_techs(world).contains(&tech)
+}
+
+/// Проверить что технология изучена.
+/// None - технология не нужна.
+pub fn require_tech(
+    world: &World,
+    tech: Option<Tech>,
+) -> Result<(), SamosborError> {
+    match tech {
+        Some(tech) if !is_researched(world, tech) =>
+            Err(SamosborError::NotResearched(tech)),
+        _ => Ok(()),
+    }
+}
+
+/// Запустить исследование.
+/// Возвращает проект исследования.
+pub fn start_research(
+    world: &mut World,
+    tech: Tech,
+    priority: TaskPriority,
+) -> Result<Entity, SamosborError> {
+    let mut projects_q = <(&Tech, &ResearchStatus)>::query();
+    if let Some((_, status)) = projects_q
+        .iter(world)
+        .find(|(t, _)| **t == tech)
+    {
+        return Err(match status {
+            ResearchStatus::Done => SamosborError::AlreadyResearched(tech),
+            ResearchStatus::InProgress => SamosborError::AlreadyResearching(tech),
+        })
+    }
+    for prerequisite in tech_prerequisites(tech).iter() {
+        require_tech(world, Some(*prerequisite))?;
+    }
+    let project = world.push((
+        tech,
+        ResearchStatus::InProgress,
+    ));
+    for task_meta in tech_requirements(tech).iter() {
+        push_task(world, BelongsToResearch(project), *task_meta, priority);
+    };
+    Ok(project)
+}
+
+/// Закрыть исследования, по которым не осталось задач.
+/// Предполагается что завершенные таски удалены предыдущей системой.
+pub fn complete_research(
+    world: &mut World,
+    _resources: &mut Resources,
+) {
+    let mut in_progress: HashSet<Entity> = HashSet::new();
+    let mut in_progress_q = <&BelongsToResearch>::query()
+        .filter(component::<TaskMeta>());
+    for BelongsToResearch(project) in in_progress_q.iter(world) {
+        in_progress.insert(*project);
+    };
+    let mut projects_q = <(Entity, &mut ResearchStatus)>::query();
+    for (project, status) in projects_q.iter_mut(world) {
+        if *status == ResearchStatus::InProgress
+            && !in_progress.contains(project)
+        {
+            *status = ResearchStatus::Done;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::area::*;
+    use crate::turn::{assign_tasks, process_tasks, clean_up_completed_tasks};
+
+    fn work(world: &mut World, shifts: usize) {
+        let mut resources = Resources::default();
+        for _ in 0..shifts {
+            assign_tasks(world, &mut resources);
+            process_tasks(world, &mut resources);
+            clean_up_completed_tasks(world, &mut resources);
+            complete_research(world, &mut resources);
+        }
+    }
+
+    fn institute(world: &mut World, spec: SciSpec) {
+        let lab = spawn_ready_germ(world, Tier::T1, AreaType::Science).unwrap();
+        spawn_ready_stationary(world, Stationary::LabT1, lab);
+        spawn_comrad(world, Profession::Scientist, Tier::T1, MilitaryDep::None, spec, lab).unwrap();
+    }
+
+    #[test]
+    fn test_every_rolled_spec_has_techs() {
+        let mut dice = Dice::new(0);
+        for _ in 0..100 {
+            let spec = random_sci_spec(&mut dice);
+            assert!(TECHS.iter().any(|tech| tech_spec(*tech) == spec));
+        }
+    }
+
+    #[test]
+    fn test_prerequisites_first() {
+        let mut world = World::default();
+        assert_eq!(
+            start_research(&mut world, Tech::Electronics, TaskPriority(0)),
+            Err(SamosborError::NotResearched(Tech::Vulcanization)),
+        );
+    }
+
+    #[test]
+    fn test_researched_by_own_spec() {
+        let mut world = World::default();
+        institute(&mut world, SciSpec::Industry);
+        start_research(&mut world, Tech::Vulcanization, TaskPriority(0)).unwrap();
+        work(&mut world, 10);
+        assert!(is_researched(&world, Tech::Vulcanization));
+        assert_eq!(
+            start_research(&mut world, Tech::Vulcanization, TaskPriority(0)),
+            Err(SamosborError::AlreadyResearched(Tech::Vulcanization)),
+        );
+    }
+
+    #[test]
+    fn test_other_spec_cant_research() {
+        let mut world = World::default();
+        institute(&mut world, SciSpec::Bio);
+        start_research(&mut world, Tech::Vulcanization, TaskPriority(0)).unwrap();
+        work(&mut world, 10);
+        assert!(!is_researched(&world, Tech::Vulcanization));
+    }
+}
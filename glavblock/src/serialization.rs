@@ -0,0 +1,248 @@
+use std::fs;
+use std::path::Path;
+
+use legion::*;
+use legion::serialize::{Canon, Registry};
+use serde::de::DeserializeSeed;
+use serde_json;
+use serde_json::value::Value;
+use serde_json::map::Map;
+use serde_json::error::Error;
+
+use crate::core::*;
+use crate::area::*;
+use crate::people::*;
+use crate::production::*;
+use crate::resources::*;
+use crate::storage::*;
+use crate::recipes::*;
+use crate::samosbor::*;
+use crate::science::*;
+use crate::expedition::*;
+use crate::military::*;
+use crate::unrest::*;
+use crate::nutrition::RationPolicy;
+use crate::population::{Child, RefugeeGroup};
+use crate::housing::Homeless;
+use crate::utilities::{UtilityConnections, Unsupplied};
+use crate::maintenance::Durability;
+use crate::deconstruction::RelocateTo;
+
+/// Версия формата сохранения.
+/// Поднимать при несовместимом изменении формата,
+/// сохранения с другой версией не читаются.
+pub const SAVE_VERSION: u64 = 17;
+
+/// Все компоненты колонии, которые попадают в сохранение
+fn registry() -> Registry<String> {
+    let mut registry = Registry::<String>::default();
+    // area
+    registry.register::<AreaType>("area_type".to_string());
+    registry.register::<AreaCapacity>("area_capacity".to_string());
+    registry.register::<AreaOccupied>("area_occupied".to_string());
+    registry.register::<BelongsToRoom>("belongs_to_room".to_string());
+    // people
+    registry.register::<Profession>("profession".to_string());
+    registry.register::<Tier>("tier".to_string());
+    registry.register::<MilitaryDep>("military_dep".to_string());
+    registry.register::<SciSpec>("sci_spec".to_string());
+    registry.register::<Satiety>("satiety".to_string());
+    registry.register::<Mood>("mood".to_string());
+    registry.register::<Experience>("experience".to_string());
+    registry.register::<Injured>("injured".to_string());
+    // production
+    registry.register::<Germ>("germ".to_string());
+    registry.register::<Stationary>("stationary".to_string());
+    registry.register::<StationaryStatus>("stationary_status".to_string());
+    registry.register::<BelongsToStationary>("belongs_to_stationary".to_string());
+    registry.register::<TaskMeta>("task_meta".to_string());
+    registry.register::<TaskPriority>("task_priority".to_string());
+    registry.register::<TaskTotal>("task_total".to_string());
+    registry.register::<Paused>("paused".to_string());
+    registry.register::<ResumeStatus>("resume_status".to_string());
+    registry.register::<AssignedTask>("assigned_task".to_string());
+    // recipes
+    registry.register::<Recipe>("recipe".to_string());
+    registry.register::<BelongsToOrder>("belongs_to_order".to_string());
+    // samosbor
+    registry.register::<Contamination>("contamination".to_string());
+    registry.register::<Mutated>("mutated".to_string());
+    // science
+    registry.register::<Tech>("tech".to_string());
+    registry.register::<ResearchStatus>("research_status".to_string());
+    registry.register::<BelongsToResearch>("belongs_to_research".to_string());
+    // expedition
+    registry.register::<Expedition>("expedition".to_string());
+    registry.register::<OnExpedition>("on_expedition".to_string());
+    // military
+    registry.register::<Squad>("squad".to_string());
+    registry.register::<SquadMember>("squad_member".to_string());
+    registry.register::<Equipped>("equipped".to_string());
+    registry.register::<DispatchedTo>("dispatched_to".to_string());
+    // unrest
+    registry.register::<OnStrike>("on_strike".to_string());
+    registry.register::<Riot>("riot".to_string());
+    registry.register::<Child>("child".to_string());
+    registry.register::<RefugeeGroup>("refugee_group".to_string());
+    registry.register::<Homeless>("homeless".to_string());
+    registry.register::<UtilityConnections>("utility_connections".to_string());
+    registry.register::<Unsupplied>("unsupplied".to_string());
+    registry.register::<Durability>("durability".to_string());
+    registry.register::<RelocateTo>("relocate_to".to_string());
+    // storage
+    registry.register::<Shelf>("shelf".to_string());
+    registry.register::<Barrel>("barrel".to_string());
+    registry.register::<Container>("container".to_string());
+    registry.register::<Option<Resource>>("stored_resource".to_string());
+    registry.register::<VolumeOccupied>("volume_occupied".to_string());
+    registry
+}
+
+fn stringify(x: Error) -> SamosborError { SamosborError::Save(format!("error: {}", x)) }
+
+/// Сериализовать колонию целиком: мир и глобальные ресурсы
+pub fn serialize_colony(
+    world: &World,
+    resources: &Resources,
+) -> Result<Value, SamosborError> {
+    let registry = registry();
+    let entity_serializer = Canon::default();
+    let world_serialized = serde_json::to_value(
+        world.as_serializable(any(), &registry, &entity_serializer)
+    ).map_err(stringify)?;
+    let dice_serialized = match resources.get::<Dice>() {
+        Some (dice) => serde_json::to_value(&*dice).map_err(stringify)?,
+        None => return Err(SamosborError::Save("No dice here".to_string())),
+    };
+    let clock_serialized = match resources.get::<SamosborClock>() {
+        Some (clock) => serde_json::to_value(*clock).map_err(stringify)?,
+        None => return Err(SamosborError::Save("No samosbor clock here".to_string())),
+    };
+    let rations_serialized = match resources.get::<RationPolicy>() {
+        Some (policy) => serde_json::to_value(&*policy).map_err(stringify)?,
+        None => return Err(SamosborError::Save("No ration policy here".to_string())),
+    };
+    let mut result = Map::new();
+    result.insert("version".to_string(), Value::from(SAVE_VERSION));
+    result.insert("world".to_string(), world_serialized);
+    result.insert("dice".to_string(), dice_serialized);
+    result.insert("samosbor_clock".to_string(), clock_serialized);
+    result.insert("ration_policy".to_string(), rations_serialized);
+    Ok(Value::Object(result))
+}
+
+/// Поднять колонию из сериализованного состояния
+pub fn deserialize_colony(
+    json: Value,
+) -> Result<(World, Resources), SamosborError> {
+    match json {
+        Value::Object (state_json) => {
+            let version = state_json
+                .get(&"version".to_string())
+                .and_then(|v| v.as_u64())
+                .ok_or(SamosborError::Save("No save version here".to_string()))?;
+            if version != SAVE_VERSION {
+                return Err(SamosborError::Save(format!(
+                    "Unsupported save version {}, expected {}",
+                    version,
+                    SAVE_VERSION,
+                )));
+            }
+            let world_value = state_json.get(&"world".to_string()).ok_or(SamosborError::Save("No world here".to_string()))?;
+            let dice_value = state_json.get(&"dice".to_string()).ok_or(SamosborError::Save("No dice here".to_string()))?;
+            let clock_value = state_json.get(&"samosbor_clock".to_string()).ok_or(SamosborError::Save("No samosbor clock here".to_string()))?;
+            let rations_value = state_json.get(&"ration_policy".to_string()).ok_or(SamosborError::Save("No ration policy here".to_string()))?;
+            let registry = registry();
+            let entity_serializer = Canon::default();
+            let world = registry
+                .as_deserialize(&entity_serializer)
+                .deserialize(world_value)
+                .map_err(stringify)?;
+            let dice: Dice = serde_json::from_value(
+                dice_value.clone()
+            ).map_err(stringify)?;
+            let clock: SamosborClock = serde_json::from_value(
+                clock_value.clone()
+            ).map_err(stringify)?;
+            let rations: RationPolicy = serde_json::from_value(
+                rations_value.clone()
+            ).map_err(stringify)?;
+            let mut resources = Resources::default();
+            resources.insert(dice);
+            resources.insert(clock);
+            resources.insert(rations);
+            Ok((world, resources))
+        },
+        _ => Err(SamosborError::Save("Invalid object".to_string())),
+    }
+}
+
+/// Сохранить колонию в файл
+pub fn save_colony(
+    path: &Path,
+    world: &World,
+    resources: &Resources,
+) -> Result<(), SamosborError> {
+    let state = serialize_colony(world, resources)?;
+    let text = serde_json::to_string(&state).map_err(stringify)?;
+    fs::write(path, text).map_err(|e| SamosborError::Save(format!("error: {}", e)))
+}
+
+/// Загрузить колонию из файла
+pub fn load_colony(
+    path: &Path,
+) -> Result<(World, Resources), SamosborError> {
+    let text = fs::read_to_string(path).map_err(|e| SamosborError::Save(format!("error: {}", e)))?;
+    let state: Value = serde_json::from_str(&text).map_err(stringify)?;
+    deserialize_colony(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colony::*;
+    use crate::simulation::simulate;
+
+    #[test]
+    fn test_serialize_deserialize() {
+        let mut world = World::default();
+        let mut resources = init_resources(0);
+        init_colony(&mut world, &mut resources).unwrap();
+
+        let serialized = serialize_colony(&world, &resources).unwrap();
+        let (mut loaded, _) = deserialize_colony(serialized).unwrap(); // panics if deserialization have no success
+        assert_eq!(
+            people_by_profession(&mut world),
+            people_by_profession(&mut loaded),
+        );
+        assert_eq!(
+            what_we_have(&mut world),
+            what_we_have(&mut loaded),
+        );
+    }
+
+    #[test]
+    fn test_dice_survive_save() {
+        let mut world = World::default();
+        let mut resources = init_resources(3);
+        init_colony(&mut world, &mut resources).unwrap();
+        simulate(&mut world, &mut resources, 5, |_| ());
+
+        let serialized = serialize_colony(&world, &resources).unwrap();
+        let (_, loaded_resources) = deserialize_colony(serialized).unwrap();
+        let mut dice = resources.get_mut::<Dice>().unwrap();
+        let mut loaded_dice = loaded_resources.get_mut::<Dice>().unwrap();
+        let rolls: Vec<usize> = (0..20).map(|_| d(&mut dice, 1, 100)).collect();
+        let loaded_rolls: Vec<usize> = (0..20).map(|_| d(&mut loaded_dice, 1, 100)).collect();
+        assert_eq!(rolls, loaded_rolls);
+    }
+
+    #[test]
+    fn test_other_save_version_rejected() {
+        let world = World::default();
+        let resources = init_resources(0);
+        let mut serialized = serialize_colony(&world, &resources).unwrap();
+        serialized["version"] = Value::from(SAVE_VERSION + 1);
+        assert!(deserialize_colony(serialized).is_err());
+    }
+}
@@ -0,0 +1,209 @@
+use std::fmt;
+use std::collections::HashMap;
+
+use legion::*;
+
+use crate::people::*;
+use crate::production::*;
+use crate::resources::*;
+use crate::storage::*;
+use crate::samosbor::*;
+use crate::science::researched_techs;
+use crate::expedition::on_expedition;
+use crate::unrest::{strikers, riots};
+use crate::population::{children, pending_refugees};
+use crate::housing::{HousingReport, housing_report};
+use crate::utilities::unsupplied_stationaries;
+use crate::maintenance::broken_stationaries;
+use crate::tasks::list_tasks;
+use crate::turn::turn;
+
+/// Сводка по колонии на конец хода
+#[derive(Clone, Debug)]
+pub struct TurnReport {
+    pub turn: usize,
+    pub people: HashMap<Profession, usize>,
+    pub tasks: usize,
+    pub paused: usize,
+    pub busy: usize,
+    pub idle: usize,
+    pub on_expedition: usize,
+    pub children: usize,
+    pub refugees: usize,
+    pub housing: HousingReport,
+    pub strikers: usize,
+    pub riots: usize,
+    pub stock: HashMap<Resource, RealUnits>,
+    pub mood: usize,
+    pub satiety: usize,
+    pub samosbors: usize,
+    pub contaminated_rooms: usize,
+    pub unsupplied: usize,
+    pub broken: usize,
+    pub researched: usize,
+}
+
+/// Снять сводку с колонии
+pub fn turn_report(
+    world: &mut World,
+    resources: &Resources,
+    turn: usize,
+) -> TurnReport {
+    let samosbors = resources
+        .get::<SamosborClock>()
+        .map(|clock| clock.total)
+        .unwrap_or(0);
+    let tasks = list_tasks(world);
+    TurnReport {
+        turn,
+        people: people_by_profession(world),
+        tasks: tasks.len(),
+        paused: tasks.iter().filter(|task| task.paused).count(),
+        busy: busy_comrads(world),
+        idle: idle_comrads(world),
+        on_expedition: on_expedition(world),
+        children: children(world),
+        refugees: pending_refugees(world)
+            .iter()
+            .map(|(_, group)| group.members.len())
+            .sum(),
+        housing: housing_report(world),
+        strikers: strikers(world),
+        riots: riots(world),
+        stock: what_we_have(world),
+        mood: block_mood(world),
+        satiety: block_satiety(world),
+        samosbors,
+        contaminated_rooms: contaminated_rooms(world),
+        unsupplied: unsupplied_stationaries(world),
+        broken: broken_stationaries(world),
+        researched: researched_techs(world).len(),
+    }
+}
+
+/// Строки отчета сортируем, чтобы вывод двух прогонов
+/// можно было сравнивать diff'ом
+fn sorted_lines<K: fmt::Debug, V: fmt::Debug>(
+    map: &HashMap<K, V>,
+) -> Vec<String> {
+    let mut lines: Vec<String> = map
+        .iter()
+        .map(|(k, v)| format!("{:?}: {:?}", k, v))
+        .collect();
+    lines.sort();
+    lines
+}
+
+impl fmt::Display for TurnReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "== Ход {} ==", self.turn)?;
+        writeln!(f, "Люди:")?;
+        for line in sorted_lines(&self.people) {
+            writeln!(f, "  {}", line)?;
+        }
+        writeln!(f, "Задач в очереди: {}, на паузе: {}", self.tasks, self.paused)?;
+        writeln!(
+            f,
+            "Заняты: {}, без дела: {}, в вылазке: {}",
+            self.busy,
+            self.idle,
+            self.on_expedition,
+        )?;
+        writeln!(f, "Детей: {}, беженцев у гермы: {}", self.children, self.refugees)?;
+        writeln!(
+            f,
+            "Жилье: занято {} из {}, бездомных: {}, в тесноте ячеек: {}, не хватает площади: {}",
+            self.housing.occupied,
+            self.housing.capacity,
+            self.housing.homeless,
+            self.housing.overcrowded,
+            self.housing.shortage(),
+        )?;
+        writeln!(f, "Склад:")?;
+        for line in sorted_lines(&self.stock) {
+            writeln!(f, "  {}", line)?;
+        }
+        writeln!(f, "Настроение: {}", self.mood)?;
+        writeln!(f, "Бастуют: {}, бунтов: {}", self.strikers, self.riots)?;
+        writeln!(f, "Сытость: {}", self.satiety)?;
+        writeln!(f, "Самосборов: {}", self.samosbors)?;
+        writeln!(f, "Залито помещений: {}", self.contaminated_rooms)?;
+        writeln!(f, "Стоит без коммуникаций: {}, сломано: {}", self.unsupplied, self.broken)?;
+        write!(f, "Изучено технологий: {}", self.researched)
+    }
+}
+
+/// Прогнать колонию на заданное количество ходов без отрисовки.
+/// После каждого хода отдает сводку в `on_turn`.
+pub fn simulate<F>(
+    world: &mut World,
+    resources: &mut Resources,
+    turns: usize,
+    mut on_turn: F,
+) where F: FnMut(&TurnReport) {
+    for n in 1..=turns {
+        turn(world, resources);
+        let report = turn_report(world, resources, n);
+        on_turn(&report);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colony::*;
+
+    /// Прогнать стартовую колонию и собрать сводки
+    fn run(seed: u64, turns: usize) -> Vec<String> {
+        let mut world = World::default();
+        let mut resources = init_resources(seed);
+        init_colony(&mut world, &mut resources).unwrap();
+        let mut reports = Vec::new();
+        simulate(
+            &mut world,
+            &mut resources,
+            turns,
+            |report| reports.push(report.to_string()),
+        );
+        reports
+    }
+
+    #[test]
+    fn test_simulate_reports_every_turn() {
+        let mut world = World::default();
+        let mut resources = init_resources(0);
+        init_colony(&mut world, &mut resources).unwrap();
+        let mut turns = Vec::new();
+        simulate(
+            &mut world,
+            &mut resources,
+            5,
+            |report| {
+                assert!(!report.people.is_empty());
+                turns.push(report.turn);
+            },
+        );
+        assert_eq!(turns, vec![1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_same_seed_same_colony() {
+        assert_eq!(run(7, 30), run(7, 30));
+    }
+
+    #[test]
+    fn test_default_colony_survives() {
+        let mut world = World::default();
+        let mut resources = init_resources(0);
+        init_colony(&mut world, &mut resources).unwrap();
+        simulate(
+            &mut world,
+            &mut resources,
+            START_FOOD_TURNS / 2,
+            |report| {
+                assert!(report.people.values().sum::<usize>() > 0);
+                assert!(report.stock.contains_key(&Resource::ConcentratT1));
+            },
+        );
+    }
+}
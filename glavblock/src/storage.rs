@@ -1,19 +1,24 @@
 use std::cmp::min;
 use std::ops::*;
 use std::collections::HashMap;
+use std::collections::HashSet;
 
 use legion::*;
+use legion::storage::Component;
+use serde::{Deserialize, Serialize};
 
 use crate::area::*;
 use crate::core::*;
 use crate::resources::*;
+use crate::production::*;
+use crate::definitions::definitions;
 
 /// Вместимость контейнера(единицы объема)
-#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct VolumeCapacity (pub usize);
 
 /// Занятое место (единицы объема)
-#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct VolumeOccupied (pub usize);
 
 /// Для случаев когда надо заполнить или опустошить контейнер
@@ -38,7 +43,7 @@ impl SubAssign for VolumeOccupied {
 }
 
 /// Вещественные единицы (количество ресурса)
-#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct RealUnits (pub usize);
 
 impl SubAssign for RealUnits {
@@ -62,32 +67,36 @@ impl Sub for RealUnits {
 
 /// Стандартная вместимость контейнера(полки)
 /// в единицах объема
-const CONTAINER_VOLUME:VolumeCapacity = VolumeCapacity (1000);
+pub const CONTAINER_VOLUME:VolumeCapacity = VolumeCapacity (1000);
 
 /// Сколько места занимает напольный контейнер
 const CONTAINER_SIZE:AreaOccupied = AreaOccupied (5);
 
+/// Сколько полок на одном стеллаже
+pub const SHELVES_PER_RACK: usize = 4;
+
 /// Тип ресурса. Текучий или твердый.
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-enum StorageType {
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StorageType {
     Solid,
     Fluid,
 }
 
 /// Полка на стеллаже
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Shelf ();
 
 /// Чан (как контейнер для хранения, не как постройка)
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Barrel ();
 
 /// Контейнер на полу
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-struct Container ();
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Container ();
 
 /// Тип хранения ресурса
-fn container_type (
+/// Встроенное значение, используется если нет файла определений.
+pub(crate) fn builtin_container_type (
     resource: Resource
 ) -> StorageType {
     match resource {
@@ -120,11 +129,19 @@ fn container_type (
     }
 }
 
+/// Тип хранения ресурса
+fn container_type (
+    resource: Resource
+) -> StorageType {
+    definitions().resource(resource).storage
+}
+
 /// Каждый контейнер имеет вместимость 1000 объемных единиц.
 /// контейнер занимает 5 единиц площади
 /// Функция говорит сколько единиц объема занимает одна
 /// вещественная единица конкретного ресурса.
-pub fn piece_size (
+/// Встроенное значение, используется если нет файла определений.
+pub(crate) fn builtin_piece_size (
     resource: Resource,
 ) -> VolumeOccupied {
     match resource {
@@ -157,15 +174,22 @@ pub fn piece_size (
     }
 }
 
+/// Сколько единиц объема занимает одна
+/// вещественная единица конкретного ресурса.
+pub fn piece_size (
+    resource: Resource,
+) -> VolumeOccupied {
+    definitions().resource(resource).piece_size
+}
+
 /// Какой объем нужен для хранения этого количества ресурса
 pub fn real2volume(
     resource: Resource,
     amount: RealUnits,
 ) -> VolumeOccupied {
     let size = piece_size(resource);
-    match (size, amount) {
-        (VolumeOccupied (volume), RealUnits (units)) => VolumeOccupied(volume*units),
-    }
+    let (VolumeOccupied (volume), RealUnits (units)) = (size, amount);
+    VolumeOccupied(volume*units)
 }
 
 /// Сколько вещественных единиц ресурса в этом объеме
@@ -174,9 +198,58 @@ pub fn volume2real(
     volume: VolumeOccupied,
 ) -> RealUnits {
     let size = piece_size(resource);
-    match (size, volume) {
-        (VolumeOccupied (size_), VolumeOccupied (volume_)) => RealUnits(volume_ / size_),
+    let (VolumeOccupied (size_), VolumeOccupied (volume_)) = (size, volume);
+    RealUnits(volume_ / size_)
+}
+
+/// Свободный объем контейнера
+fn free_volume(occupied: VolumeOccupied) -> VolumeOccupied {
+    VolumeOccupied(CONTAINER_VOLUME.0.saturating_sub(occupied.0))
+}
+
+/// Разложить ресурс по контейнерам вида `T`.
+/// Сначала доливаются контейнеры, где этот ресурс уже лежит,
+/// потом занимаются пустые.
+/// Возвращает количество ресурса которое не удалось разместить.
+fn fill_containers<T: Component> (
+    world: &mut World,
+    amount: RealUnits,
+    resource: Resource,
+) -> RealUnits {
+    if amount == RealUnits (0) {
+        return RealUnits (0)
+    }
+    let mut query = <(
+        &mut Option<Resource>,
+        &mut VolumeOccupied,
+    )>::query()
+        // Бездомные кучи не доливаем: им место на складе
+        .filter(component::<T>() & component::<BelongsToRoom>());
+    let mut containers = query
+        .iter_mut(world)
+        .filter(|(res, _)| res.is_none() || **res == Some(resource))
+        .collect::<Vec<(
+            &mut Option<Resource>,
+            &mut VolumeOccupied,
+        )>>();
+    // Начатые контейнеры вперед
+    containers.sort_by_key(|(res, _)| res.is_none());
+    let mut not_deposited = amount;
+    for (res, occupied) in containers.iter_mut() {
+        let fits = volume2real(resource, free_volume(**occupied));
+        let deposit = min(fits, not_deposited);
+        if deposit == RealUnits (0) {
+            continue
+        }
+        **res = Some(resource);
+        occupied.0 += real2volume(resource, deposit).0;
+        not_deposited -= deposit;
+        if not_deposited == RealUnits (0) {
+            // Все распределено
+            break
+        }
     }
+    not_deposited
 }
 
 /// Хранить ресурс в чане.
@@ -187,45 +260,8 @@ fn store_in_barrels (
     amount: RealUnits,
     resource: Resource,
 ) -> RealUnits {
-    if amount == RealUnits (0) {
-        return RealUnits (0)
-    } else {
-        let mut query = <(
-            &Barrel,
-            &mut Option<Resource>,
-            &mut VolumeOccupied
-        )>::query();
-        let free_barrels = query
-            .iter_mut(world)
-            .filter(|(_, res, _)| **res == None);
-        let mut not_deposited = amount;
-        for (_, res, occupied) in free_barrels {
-            *res = Some(resource);
-
-            *occupied = min(
-                VolumeOccupied::from(CONTAINER_VOLUME),
-                real2volume(
-                    resource,
-                    not_deposited.clone(),
-                ),
-            );
-            if occupied.clone() == VolumeOccupied (0) {
-                // Все распределено
-                break
-            } else {
-                // Что-то осталось.
-                // Заходим на следующий виток.
-                not_deposited -= volume2real(
-                    resource,
-                    occupied.clone(),
-                );
-            }
-        }
-        // Либо все распределили,
-        // либо бочки закончились
-        // и возвращаем сколько не разместили
-        not_deposited
-    }}
+    fill_containers::<Barrel>(world, amount, resource)
+}
 
 /// Хранить ресурс на полке.
 /// Возвращает количество ресурса которое не удалось положить
@@ -235,101 +271,73 @@ fn store_on_shelves (
     amount: RealUnits,
     resource: Resource
 ) -> RealUnits {
-    if amount == RealUnits (0) {
-        return RealUnits (0)
-    } else {
-        let mut query = <(
-            &Shelf,
-            &mut Option<Resource>,
-            &mut VolumeOccupied,
-        )>::query();
-        let free_shelves = query
-            .iter_mut(world)
-            .filter(|(_, res, _)| **res == None);
-        let mut not_deposited = amount;
-        for (_, res, occupied) in free_shelves {
-            *res = Some(resource);
-            *occupied = min(
-                VolumeOccupied::from(CONTAINER_VOLUME),
-                real2volume(
-                    resource,
-                    not_deposited.clone(),
-                ),
-            );
-            if occupied.clone() == VolumeOccupied (0) {
-                // Все распределено
-                break
-            } else {
-                // Что-то осталось.
-                // Заходим на следующий виток.
-                not_deposited -= volume2real(
-                    resource,
-                    occupied.clone(),
-                );
-            }
-        }
-        // Либо все распределили
-        // либо полки закончились
-        //и возвращаем сколько не разместили
-        not_deposited
-}}
+    fill_containers::<Shelf>(world, amount, resource)
+}
 
-/// Хранить ресурсы в ящиках на полу
+/// Хранить ресурсы в ящиках на полу.
+/// Сначала досыпаем в уже стоящие ящики, потом ставим новые.
 /// Возвращает количество ресурса которое не вместилось
 fn store_on_floor (
     world: &mut World,
     amount: RealUnits,
     resource: Resource,
 ) -> RealUnits {
-    if amount == RealUnits(0) {
-        return RealUnits(0)
-    } else {
-        let mut not_deposited = amount;
-        while not_deposited > RealUnits (0) {
-            let mbroom = get_sufficent_room(
-                world,
-                CONTAINER_SIZE,
-                AreaType::Party, // партийный склад!
-            );
-            match mbroom {
-                None => break, // нет на складах места.
-                Some (room) => {
-                    let occupied = min(
-                        VolumeOccupied::from(
-                            CONTAINER_VOLUME
-                        ),
-                        real2volume(
-                            resource,
-                            not_deposited.clone(),
-                        ),
-                    );
-                    not_deposited -= volume2real(
+    let mut not_deposited = fill_containers::<Container>(
+        world,
+        amount,
+        resource,
+    );
+    while not_deposited > RealUnits (0) {
+        let mbroom = get_sufficent_room(
+            world,
+            CONTAINER_SIZE,
+            AreaType::Party, // партийный склад!
+        );
+        match mbroom {
+            None => break, // нет на складах места.
+            Some (room) => {
+                let occupied = min(
+                    VolumeOccupied::from(
+                        CONTAINER_VOLUME
+                    ),
+                    real2volume(
                         resource,
-                        occupied.clone(),
-                    );
-                    world.push((
-                        Container,
-                        Some(resource),
-                        BelongsToRoom(room),
-                        occupied,
-                    ));
-                },
-            }
-        };
-        not_deposited
-    }
+                        not_deposited,
+                    ),
+                );
+                let deposited = volume2real(
+                    resource,
+                    occupied,
+                );
+                if deposited == RealUnits (0) {
+                    // Одна штука больше ящика
+                    break
+                }
+                not_deposited -= deposited;
+                world.push((
+                    Container(),
+                    Some(resource),
+                    BelongsToRoom(room),
+                    CONTAINER_SIZE,
+                    real2volume(resource, deposited),
+                ));
+            },
+        }
+    };
+    not_deposited
 }
 
 /// Положить ресурс на хранение.
-/// Возвращает количество невместившегося ресурса.
+/// Если места не хватило - то что влезло остается на складе,
+/// а в ошибке сказано сколько не поместилось.
 pub fn put_resource(
     world: &mut World,
     resource: Resource,
     amount: RealUnits,
-) -> RealUnits {
+) -> Result<(), SamosborError> {
     // Тип хранения - твердый или текучий
     let container_type = container_type(resource);
-    match container_type {
+    let rest = match container_type {
         // Твердые материалы можно хранить на полках и в кучах на полу
         StorageType::Solid => {
             let rest = store_on_shelves(
@@ -345,11 +353,16 @@ pub fn put_resource(
             amount,
             resource,
         ),
+    };
+    if rest == RealUnits(0) {
+        Ok(())
+    } else {
+        Err(SamosborError::StorageOverflow { resource, rest })
     }
 }
 
 /// Забрать ресурс из контейнеров на полу.
-/// Удалить контейнеры и освободить место.
+/// Удалить опустевшие контейнеры и освободить место.
 /// Списание "с пола" всегда должно выполняться перед
 /// списанием из мест хранения.
 /// проверка наличия не проводится
@@ -360,66 +373,41 @@ fn writeoff_from_floor (
     amount: RealUnits,
 ) -> RealUnits {
     let mut writeoff_query = <(
-        &Option<Resource>,
-        &Container,
-        &Entity,
+        Entity,
+        &mut Option<Resource>,
         &mut VolumeOccupied,
-    )>::query();
-
-    let mut empty_containers = Vec::new();
-    let mut writed_off = RealUnits(0);
-    let mut containers = writeoff_query
-        .iter_mut(world)
-        .filter(|(res, _, _, _)| **res == Some(resource))
-        .map(|(_, _, e, v)| (e,v))
-        .collect::<Vec<(
-            &Entity,
-            &mut VolumeOccupied,
-        )>>();
-    // здесь задумана сортировка
-    // от более заполненных к менее
-    // поменять местами выражение если не сработает
-    containers.sort_by(
-        |(_,occ1), (_, occ2)| occ2.cmp(occ1)
+    )>::query()
+        .filter(component::<Container>());
+    let mut touched: HashSet<Entity> = HashSet::new();
+    let rest = writeoff_from_containers(
+        writeoff_query
+            .iter_mut(world)
+            .filter(|(_, res, _)| **res == Some(resource))
+            .map(|(e, res, occ)| {
+                touched.insert(*e);
+                (res, occ)
+            })
+            .collect(),
+        resource,
+        amount,
     );
-
-    for (entity, occ) in containers.iter_mut() {
-        let required_pieces = amount - writed_off;
-        let required_volume = real2volume(
-            resource,
-            required_pieces,
-        );
-        // если в контейнере меньше чем надо
-        // или ровно сколько надо
-        if occ.clone () <= required_volume {
-            // забираем из него все
-            occ.0 = 0;
-            writed_off += volume2real(resource, occ.clone());
-            // планируем удаление контейнера
-            empty_containers.push(entity.clone());
-            // идем в следующий контейнер
-        } else {
-            // в контейнере больше чем надо
-            // забираем оттуда требуемое количество
-            **occ -= required_volume;
-            writed_off += volume2real(
-                resource,
-                required_volume,
-            );
-            break
-        }
-    }
-    for entity in empty_containers.iter() {
+    // Ящики, которые опустели - убираем
+    let mut occupancy_query = <(Entity, &Option<Resource>)>::query()
+        .filter(component::<Container>());
+    let to_remove: Vec<Entity> = occupancy_query
+        .iter(world)
+        .filter(|(e, res)| res.is_none() && touched.contains(e))
+        .map(|(e, _)| *e)
+        .collect();
+    for entity in to_remove.iter() {
         world.remove (*entity);
     }
-    amount - writed_off
+    rest
 }
 
 /// Забрать ресурс из чанов или полок.
 /// проверка наличия не проводится
 /// Возвращает количество, которое не удалось забрать.
-/// TODO: Количество которое не удалось забрать
-/// должно быть гарантированно равно 0
 fn writeoff_from_storage (
     world: &mut World,
     resource: Resource,
@@ -428,69 +416,81 @@ fn writeoff_from_storage (
     let mut writeoff_query = <(
         &mut Option<Resource>,
         &mut VolumeOccupied,
-    )>::query();
+    )>::query()
+        .filter(!component::<Container>());
+    writeoff_from_containers(
+        writeoff_query
+            .iter_mut(world)
+            .filter(|(res, _)| **res == Some(resource))
+            .collect(),
+        resource,
+        amount,
+    )
+}
 
-    let mut writed_off = RealUnits(0);
-    let mut containers = writeoff_query
-        .iter_mut(world)
-        .collect::<Vec<(
-            &mut Option<Resource>,
-            &mut VolumeOccupied,
-        )>>();
-    // здесь задумана сортировка
-    // от более заполненных к менее
-    // поменять местами выражение если не сработает
+/// Выбрать ресурс из контейнеров, где лежит именно он.
+/// Опустевший контейнер помечается пустым.
+/// Возвращает количество, которое не удалось забрать.
+fn writeoff_from_containers (
+    mut containers: Vec<(
+        &mut Option<Resource>,
+        &mut VolumeOccupied,
+    )>,
+    resource: Resource,
+    amount: RealUnits,
+) -> RealUnits {
+    // от менее заполненных к более,
+    // чтобы быстрее освобождать контейнеры
     containers.sort_by(
-        |(_,occ1), (_, occ2)| occ2.cmp(occ1)
+        |(_,occ1), (_, occ2)| occ1.cmp(occ2)
     );
-
+    let mut writed_off = RealUnits(0);
     for (res, occ) in containers.iter_mut() {
         let required_pieces = amount - writed_off;
-        let required_volume = real2volume(
-            resource,
-            required_pieces,
-        );
-        // если в контейнере меньше чем надо
-        // или ровно сколько надо
-        if **occ <= required_volume {
+        if required_pieces == RealUnits(0) {
+            break
+        }
+        let stored_pieces = volume2real(resource, **occ);
+        if stored_pieces <= required_pieces {
             // забираем из него все
+            writed_off += stored_pieces;
             occ.0 = 0;
-            writed_off += volume2real(resource, **occ);
-            // чистим контейнер
             **res = None;
-            // идем в следующий контейнер
         } else {
             // в контейнере больше чем надо
             // забираем оттуда требуемое количество
-            **occ -= required_volume;
-            writed_off += volume2real(
-                resource,
-                required_volume,
-            );
-            break
+            **occ -= real2volume(resource, required_pieces);
+            writed_off += required_pieces;
         }
     }
     amount - writed_off
 }
 
-
 /// Изъять ресурс, освободить пространство.
 pub fn writeoff (
     world: &mut World,
     resource: Resource,
     amount: RealUnits,
-) {
+) -> Result<(), SamosborError> {
+    let available = how_much_we_have(world, resource);
+    if available < amount {
+        return Err(SamosborError::NotEnoughResources {
+            resource,
+            required: amount,
+            available,
+        })
+    }
     let rest = writeoff_from_floor(
         world,
         resource,
         amount,
     );
-    let rest_ = writeoff_from_storage(
+    let _ = writeoff_from_storage(
         world,
         resource,
         rest,
     );
-    assert_eq!(rest_, RealUnits(0));
+    Ok(())
 }
 
 
@@ -520,9 +520,9 @@ pub fn what_we_have(
         &Option<Resource>,
         &VolumeOccupied
     )>::query();
-    for (mbRes, vol) in deposit_query.iter (world) {
-        if let Some(res) = mbRes {
-            let mut vol_ = result
+    for (maybe_res, vol) in deposit_query.iter (world) {
+        if let Some(res) = maybe_res {
+            let vol_ = result
                 .entry(*res)
                 .or_insert(RealUnits(0));
             *vol_ += volume2real(*res, *vol);
@@ -531,6 +531,228 @@ pub fn what_we_have(
     result
 }
 
+/// Сколько мест хранения дает стационарка
+fn stationary_containers(
+    stationary: Stationary,
+) -> (Option<ContainerKind>, usize) {
+    match stationary {
+        Stationary::Rack => (Some(ContainerKind::Shelf), SHELVES_PER_RACK),
+        Stationary::Barrel => (Some(ContainerKind::Barrel), 1),
+        _ => (None, 0),
+    }
+}
+
+/// Введенная в эксплуатацию стационарка обзаводится
+/// местами хранения: стеллаж - полками, чан - емкостью.
+/// Места хранения стоят в том же помещении что и стационарка.
+pub fn spawn_stationary_containers(
+    world: &mut World,
+    stationary_entity: Entity,
+) {
+    let (stationary, room) = match world.entry_ref(stationary_entity) {
+        Ok(entry) => match (
+            entry.get_component::<Stationary>(),
+            entry.get_component::<BelongsToRoom>(),
+        ) {
+            (Ok(stationary), Ok(room)) => (*stationary, *room),
+            _ => return,
+        },
+        Err(_) => return,
+    };
+    let belongs = BelongsToStationary(stationary_entity);
+    let empty: Option<Resource> = None;
+    match stationary_containers(stationary) {
+        (Some(ContainerKind::Shelf), count) => for _ in 0..count {
+            world.push((Shelf(), empty, VolumeOccupied(0), room, belongs));
+        },
+        (Some(ContainerKind::Barrel), count) => for _ in 0..count {
+            world.push((Barrel(), empty, VolumeOccupied(0), room, belongs));
+        },
+        _ => (),
+    }
+}
+
+/// Свалить ресурс бездомной кучей: ящики без помещения.
+/// Ресурс учитывается и списывается, но места на складе не занимает
+/// и висит в ведомости как бездомный.
+fn dump_homeless(
+    world: &mut World,
+    resource: Resource,
+    amount: RealUnits,
+) {
+    let per_container = volume2real(
+        resource,
+        VolumeOccupied::from(CONTAINER_VOLUME),
+    );
+    let mut rest = amount;
+    while rest > RealUnits(0) {
+        let deposit = min(rest, per_container);
+        world.push((
+            Container(),
+            Some(resource),
+            real2volume(resource, deposit),
+        ));
+        rest -= deposit;
+    }
+}
+
+/// Положить ресурс на хранение, а что не влезло на склады -
+/// свалить бездомной кучей, чтобы ничего не пропало.
+/// Возвращает сколько ушло в кучу.
+pub(crate) fn put_or_dump(
+    world: &mut World,
+    resource: Resource,
+    amount: RealUnits,
+) -> RealUnits {
+    match put_resource(world, resource, amount) {
+        Err(SamosborError::StorageOverflow { resource, rest }) => {
+            dump_homeless(world, resource, rest);
+            rest
+        },
+        _ => RealUnits(0),
+    }
+}
+
+/// Освободить места хранения стационарки перед демонтажом.
+/// Содержимое перекладывается в другие места хранения,
+/// что не влезло - остается бездомной кучей.
+pub fn release_stationary_containers(
+    world: &mut World,
+    stationary_entity: Entity,
+) {
+    let mut query = <(
+        Entity,
+        &BelongsToStationary,
+        &Option<Resource>,
+        &VolumeOccupied,
+    )>::query();
+    let containers: Vec<(Entity, Option<Resource>, VolumeOccupied)> = query
+        .iter(world)
+        .filter(|(_, BelongsToStationary(s), _, _)| *s == stationary_entity)
+        .map(|(e, _, res, occ)| (*e, *res, *occ))
+        .collect();
+    for (entity, _, _) in containers.iter() {
+        world.remove(*entity);
+    }
+    for (_, res, occ) in containers.iter() {
+        if let Some(res) = res {
+            let amount = volume2real(*res, *occ);
+            if let Err(SamosborError::StorageOverflow { resource, rest }) =
+                put_resource(world, *res, amount)
+            {
+                dump_homeless(world, resource, rest);
+            }
+        }
+    }
+}
+
+/// Вид места хранения
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ContainerKind {
+    Shelf, // полка на стеллаже
+    Barrel, // чан
+    Floor, // ящик на полу
+}
+
+/// Сводка по местам хранения одного вида в одном помещении
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StorageEntry {
+    pub containers: usize,
+    pub stored: HashMap<Resource, RealUnits>,
+    pub free: VolumeCapacity,
+}
+
+/// Ведомость склада: что где лежит.
+/// Бездомное - то, что лежит в контейнерах
+/// без помещения или в помещении которого больше нет.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StorageLedger {
+    pub rooms: HashMap<Entity, HashMap<ContainerKind, StorageEntry>>,
+    pub homeless: HashMap<Resource, RealUnits>,
+}
+
+impl StorageLedger {
+    /// Сколько этого ресурса в каждом помещении
+    pub fn by_room(
+        &self,
+        resource: Resource,
+    ) -> HashMap<Entity, RealUnits> {
+        let mut result = HashMap::new();
+        for (room, kinds) in self.rooms.iter() {
+            for entry in kinds.values() {
+                if let Some(amount) = entry.stored.get(&resource) {
+                    *result.entry(*room).or_insert(RealUnits(0)) += *amount;
+                }
+            }
+        }
+        result
+    }
+}
+
+/// Собрать в ведомость контейнеры одного вида
+fn ledger_collect<T: Component> (
+    world: &World,
+    kind: ContainerKind,
+    ledger: &mut StorageLedger,
+) {
+    let mut query = <(
+        &Option<Resource>,
+        &VolumeOccupied,
+        Option<&BelongsToRoom>,
+    )>::query()
+        .filter(component::<T>());
+    for (res, occupied, room) in query.iter(world) {
+        let room = room
+            .map(|BelongsToRoom(room)| *room)
+            .filter(|room| world.entry_ref(*room).is_ok());
+        match room {
+            Some(room) => {
+                let entry = ledger.rooms
+                    .entry(room)
+                    .or_default()
+                    .entry(kind)
+                    .or_default();
+                entry.containers += 1;
+                entry.free.0 += free_volume(*occupied).0;
+                if let Some(res) = res {
+                    *entry.stored
+                        .entry(*res)
+                        .or_insert(RealUnits(0)) += volume2real(*res, *occupied);
+                }
+            },
+            None => {
+                if let Some(res) = res {
+                    *ledger.homeless
+                        .entry(*res)
+                        .or_insert(RealUnits(0)) += volume2real(*res, *occupied);
+                }
+            },
+        }
+    }
+}
+
+/// Ведомость по всем складам колонии
+pub fn storage_ledger(
+    world: &mut World,
+) -> StorageLedger {
+    let mut ledger = StorageLedger::default();
+    ledger_collect::<Shelf>(world, ContainerKind::Shelf, &mut ledger);
+    ledger_collect::<Barrel>(world, ContainerKind::Barrel, &mut ledger);
+    ledger_collect::<Container>(world, ContainerKind::Floor, &mut ledger);
+    ledger
+}
+
+/// Что хранится в конкретном помещении
+pub fn room_storage(
+    world: &mut World,
+    room: Entity,
+) -> HashMap<ContainerKind, StorageEntry> {
+    storage_ledger(world)
+        .rooms
+        .remove(&room)
+        .unwrap_or_default()
+}
+
 /// Есть ли у нас вот столько разных ресурсов
 pub fn enough_resources(
     world: &mut World,
@@ -548,16 +770,73 @@ pub fn enough_resources(
 }
 
 /// Списать ресурсы пачкой.
+/// Либо списывается все, либо ничего.
 pub fn writeoff_bunch (
     world: &mut World,
     bunch: HashMap<Resource, RealUnits>
 ) -> Result<(),SamosborError> {
-    if enough_resources(world, bunch.clone()) {
-        for (res, amount) in bunch.iter() {
-            let _ = writeoff(world, *res, *amount);
+    for (res, amount) in bunch.iter() {
+        let available = how_much_we_have(world, *res);
+        if available < *amount {
+            return Err(SamosborError::NotEnoughResources {
+                resource: *res,
+                required: *amount,
+                available,
+            })
         }
-        Ok (())
-    } else {
-        Err(SamosborError::NotEnoughResources)
+    }
+    for (res, amount) in bunch.iter() {
+        writeoff(world, *res, *amount)?;
+    }
+    Ok (())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ledger_shows_room() {
+        let mut world = World::default();
+        let stock = spawn_ready_germ(&mut world, Tier::T1, AreaType::Party).unwrap();
+        put_resource(&mut world, Resource::ConcentratT1, RealUnits(30)).unwrap();
+        let floor = &room_storage(&mut world, stock)[&ContainerKind::Floor];
+        assert_eq!(floor.containers, 1);
+        assert_eq!(floor.stored[&Resource::ConcentratT1], RealUnits(30));
+        assert_eq!(floor.free, VolumeCapacity(CONTAINER_VOLUME.0 - 30));
+        assert!(storage_ledger(&mut world).homeless.is_empty());
+    }
+
+    #[test]
+    fn test_writeoff_takes_only_its_resource() {
+        let mut world = World::default();
+        spawn_ready_germ(&mut world, Tier::T1, AreaType::Party).unwrap();
+        put_resource(&mut world, Resource::ConcentratT1, RealUnits(30)).unwrap();
+        put_resource(&mut world, Resource::ScrapT1, RealUnits(3)).unwrap();
+        writeoff(&mut world, Resource::ConcentratT1, RealUnits(30)).unwrap();
+        assert_eq!(how_much_we_have(&mut world, Resource::ConcentratT1), RealUnits(0));
+        assert_eq!(how_much_we_have(&mut world, Resource::ScrapT1), RealUnits(3));
+        assert!(writeoff(&mut world, Resource::ConcentratT1, RealUnits(1)).is_err());
+    }
+
+    #[test]
+    fn test_overflow_is_homeless() {
+        let mut world = World::default();
+        assert_eq!(put_or_dump(&mut world, Resource::ScrapT1, RealUnits(15)), RealUnits(15));
+        let ledger = storage_ledger(&mut world);
+        assert!(ledger.rooms.is_empty());
+        assert_eq!(ledger.homeless[&Resource::ScrapT1], RealUnits(15));
+        assert_eq!(how_much_we_have(&mut world, Resource::ScrapT1), RealUnits(15));
+    }
+
+    #[test]
+    fn test_homeless_pile_not_topped_up() {
+        let mut world = World::default();
+        put_or_dump(&mut world, Resource::ConcentratT1, RealUnits(10));
+        let stock = spawn_ready_germ(&mut world, Tier::T1, AreaType::Party).unwrap();
+        put_resource(&mut world, Resource::ConcentratT1, RealUnits(5)).unwrap();
+        let ledger = storage_ledger(&mut world);
+        assert_eq!(ledger.homeless[&Resource::ConcentratT1], RealUnits(10));
+        assert_eq!(ledger.by_room(Resource::ConcentratT1)[&stock], RealUnits(5));
     }
 }
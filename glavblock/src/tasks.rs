@@ -0,0 +1,272 @@
+use std::collections::HashMap;
+
+use legion::*;
+
+use crate::core::*;
+use crate::production::*;
+use crate::resources::*;
+use crate::storage::*;
+use crate::recipes::{Recipe, BelongsToOrder, recipe_inputs};
+use crate::science::BelongsToResearch;
+use crate::maintenance::repair_resources;
+use crate::deconstruction::RelocateTo;
+
+/// Чья это задача
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TaskOwner {
+    Stationary(Entity), // постройка, ремонт или разборка стационарки или гермы
+    Order(Entity), // производственный заказ
+    Research(Entity), // исследование
+}
+
+/// Задача в очереди
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TaskInfo {
+    pub task: Entity,
+    pub owner: TaskOwner,
+    pub meta: TaskMeta,
+    pub priority: TaskPriority,
+    /// Сколько работы осталось
+    pub remaining: BuildPower,
+    /// Сколько работы было при постановке
+    pub total: BuildPower,
+    pub paused: bool,
+    /// Сколько комрадов сейчас на задаче
+    pub workers: usize,
+}
+
+impl TaskInfo {
+    /// Сделано, в процентах
+    pub fn progress(&self) -> usize {
+        // Задача без работы считается сделанной
+        ((self.total.0 - self.remaining.0) * 100)
+            .checked_div(self.total.0)
+            .unwrap_or(100)
+    }
+}
+
+/// Очередь задач в порядке приоритета
+pub fn list_tasks(
+    world: &mut World,
+) -> Vec<TaskInfo> {
+    let mut workers: HashMap<Entity, usize> = HashMap::new();
+    let mut workers_q = <&AssignedTask>::query();
+    for AssignedTask(task) in workers_q.iter(world) {
+        *workers.entry(*task).or_insert(0) += 1;
+    }
+    let mut owners: HashMap<Entity, TaskOwner> = HashMap::new();
+    let mut stationary_q = <(Entity, &BelongsToStationary)>::query()
+        .filter(component::<TaskMeta>());
+    for (task, BelongsToStationary(owner)) in stationary_q.iter(world) {
+        owners.insert(*task, TaskOwner::Stationary(*owner));
+    }
+    let mut order_q = <(Entity, &BelongsToOrder)>::query()
+        .filter(component::<TaskMeta>());
+    for (task, BelongsToOrder(owner)) in order_q.iter(world) {
+        owners.insert(*task, TaskOwner::Order(*owner));
+    }
+    let mut research_q = <(Entity, &BelongsToResearch)>::query()
+        .filter(component::<TaskMeta>());
+    for (task, BelongsToResearch(owner)) in research_q.iter(world) {
+        owners.insert(*task, TaskOwner::Research(*owner));
+    }
+    let mut query = <(
+        Entity,
+        &TaskMeta,
+        &TaskPriority,
+        Option<&TaskTotal>,
+        Option<&Paused>,
+    )>::query();
+    let mut result: Vec<TaskInfo> = query
+        .iter(world)
+        .filter_map(|(task, meta, priority, total, paused)| {
+            let owner = *owners.get(task)?;
+            Some(TaskInfo {
+                task: *task,
+                owner,
+                meta: *meta,
+                priority: *priority,
+                remaining: meta.bp,
+                total: total.map(|TaskTotal(bp)| *bp).unwrap_or(meta.bp),
+                paused: paused.is_some(),
+                workers: workers.get(task).cloned().unwrap_or(0),
+            })
+        })
+        .collect();
+    result.sort_by_key(|info| info.priority);
+    result
+}
+
+/// Найти задачу в очереди
+fn task_info(
+    world: &mut World,
+    task: Entity,
+) -> Result<TaskInfo, SamosborError> {
+    list_tasks(world)
+        .into_iter()
+        .find(|info| info.task == task)
+        .ok_or(SamosborError::NoSuchEntity(task))
+}
+
+/// Поменять приоритет задачи
+pub fn set_task_priority(
+    world: &mut World,
+    task: Entity,
+    priority: TaskPriority,
+) -> Result<(), SamosborError> {
+    task_info(world, task)?;
+    if let Some(mut entry) = world.entry(task) {
+        entry.add_component(priority);
+    }
+    Ok(())
+}
+
+/// Поставить задачу на паузу. Люди с нее уйдут на следующем ходу.
+pub fn pause_task(
+    world: &mut World,
+    task: Entity,
+) -> Result<(), SamosborError> {
+    task_info(world, task)?;
+    if let Some(mut entry) = world.entry(task) {
+        entry.add_component(Paused());
+    }
+    Ok(())
+}
+
+/// Снять задачу с паузы
+pub fn resume_task(
+    world: &mut World,
+    task: Entity,
+) -> Result<(), SamosborError> {
+    task_info(world, task)?;
+    if let Some(mut entry) = world.entry(task) {
+        entry.remove_component::<Paused>();
+    }
+    Ok(())
+}
+
+/// Вернуть списанное на склад. Что не влезло - бросили у гермы.
+fn refund(
+    world: &mut World,
+    resources: HashMap<Resource, RealUnits>,
+) {
+    for (res, amount) in resources.iter() {
+        put_or_dump(world, *res, *amount);
+    }
+}
+
+/// Убрать все задачи владельца
+fn remove_tasks(
+    world: &mut World,
+    owner: TaskOwner,
+) {
+    let tasks: Vec<Entity> = list_tasks(world)
+        .iter()
+        .filter(|info| info.owner == owner)
+        .map(|info| info.task)
+        .collect();
+    for task in tasks.iter() {
+        world.remove(*task);
+    }
+}
+
+/// Отменить работу, к которой относится задача, целиком.
+/// Списанные под нее ресурсы возвращаются на склад:
+/// недостроенная стационарка убирается, заказ и исследование
+/// снимаются, ремонт и разборка прекращаются и стационарка
+/// возвращается в прежнее состояние.
+pub fn cancel_task(
+    world: &mut World,
+    task: Entity,
+) -> Result<(), SamosborError> {
+    let owner = task_info(world, task)?.owner;
+    match owner {
+        TaskOwner::Order(order) => {
+            let recipe = *world
+                .entry_ref(order)
+                .map_err(|_| SamosborError::NoSuchEntity(order))?
+                .get_component::<Recipe>()
+                .map_err(|_| SamosborError::InvalidTaskState(task))?;
+            remove_tasks(world, owner);
+            world.remove(order);
+            refund(world, recipe_inputs(recipe));
+        },
+        TaskOwner::Research(project) => {
+            remove_tasks(world, owner);
+            world.remove(project);
+        },
+        TaskOwner::Stationary(target) => {
+            let (kind, status, resume) = {
+                let entry = world
+                    .entry_ref(target)
+                    .map_err(|_| SamosborError::NoSuchEntity(target))?;
+                (
+                    entry.get_component::<Stationary>().ok().cloned(),
+                    *entry
+                        .get_component::<StationaryStatus>()
+                        .map_err(|_| SamosborError::InvalidTaskState(task))?,
+                    entry
+                        .get_component::<ResumeStatus>()
+                        .ok()
+                        .map(|ResumeStatus(status)| *status),
+                )
+            };
+            match (status, kind) {
+                (StationaryStatus::Constructing, Some(kind)) => {
+                    dismantle_stationary(world, target)?;
+                    refund(world, stationary_required_resources(kind));
+                },
+                (StationaryStatus::Constructing, None) => {
+                    // Недостроенная герма. Ресурсов под нее не списывалось.
+                    remove_tasks(world, owner);
+                    world.remove(target);
+                },
+                (StationaryStatus::UnderRepair, kind) => {
+                    remove_tasks(world, owner);
+                    if let Some(kind) = kind {
+                        refund(world, repair_resources(kind));
+                    }
+                    if let Some(mut entry) = world.entry(target) {
+                        entry.remove_component::<ResumeStatus>();
+                        entry.add_component(resume.unwrap_or(StationaryStatus::Broken));
+                    }
+                },
+                (StationaryStatus::Dismantling, _) => {
+                    remove_tasks(world, owner);
+                    if let Some(mut entry) = world.entry(target) {
+                        entry.remove_component::<ResumeStatus>();
+                        entry.remove_component::<RelocateTo>();
+                        entry.add_component(resume.unwrap_or(StationaryStatus::Ready));
+                    }
+                },
+                _ => return Err(SamosborError::InvalidTaskState(task)),
+            }
+        },
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::area::*;
+    use crate::recipes::start_recipe_task;
+
+    #[test]
+    fn test_cancel_with_full_storage_keeps_inputs() {
+        let mut world = World::default();
+        spawn_ready_germ(&mut world, Tier::T1, AreaType::Party).unwrap();
+        let workshop = spawn_ready_germ(&mut world, Tier::T1, AreaType::Industrial).unwrap();
+        spawn_ready_stationary(&mut world, Stationary::BenchToolT1, workshop);
+        put_resource(&mut world, Resource::ScrapT1, RealUnits(2)).unwrap();
+        let order = start_recipe_task(&mut world, Recipe::MechanicalComponent, TaskPriority(0)).unwrap();
+        // Склад забили под завязку
+        let overflow = put_or_dump(&mut world, Resource::Concrete, RealUnits(1000));
+        assert!(overflow > RealUnits(0));
+        let task = list_tasks(&mut world)[0].task;
+        cancel_task(&mut world, task).unwrap();
+        assert!(world.entry_ref(order).is_err());
+        assert!(list_tasks(&mut world).is_empty());
+        assert_eq!(how_much_we_have(&mut world, Resource::ScrapT1), RealUnits(2));
+    }
+}
@@ -3,127 +3,418 @@ use std::collections::HashMap;
 use std::cmp::min;
 
 use legion::*;
-use legion::world::*;
 
 use crate::core::*;
 use crate::people::*;
 use crate::production::*;
 use crate::storage::*;
-use crate::resources::*;
-
-pub type BuildPowerPool = HashMap<Profession,HashMap<Tier, BuildPower>>;
+use crate::area::*;
+use crate::recipes::complete_production_orders;
+use crate::samosbor::*;
+use crate::science::complete_research;
+use crate::expedition::*;
+use crate::military::squads_tick;
+use crate::unrest::*;
+use crate::nutrition::*;
+use crate::population::population_tick;
+use crate::housing::housing_tick;
+use crate::utilities::{Unsupplied, utilities_tick};
+use crate::maintenance::*;
+use crate::deconstruction::complete_deconstructions;
 
 pub fn turn(
     world: &mut World,
     resources: &mut Resources,
 ) {
-    calc_buildpower(world, resources);
+    utilities_tick(world, resources);
+    assign_tasks(world, resources);
     process_tasks(world, resources);
+    squads_tick(world, resources);
     clean_up_completed_tasks(world, resources);
     setup_completed_stationaries(world, resources);
+    complete_repairs(world, resources);
+    complete_deconstructions(world, resources);
+    complete_research(world, resources);
+    complete_production_orders(world, resources);
+    expedition_tick(world, resources);
+    injury_tick(world, resources);
     hunger_tick(world, resources);
     consume_concentrat(world, resources);
+    promote_comrads(world, resources);
+    population_tick(world, resources);
+    housing_tick(world, resources);
+    contamination_tick(world, resources);
+    maintenance_tick(world, resources);
+    unrest_tick(world, resources);
+    samosbor_tick(world, resources);
+}
+
+/// Может ли комрад вообще работать по задачам
+fn can_work(prof: Profession) -> bool {
+    // Ликвидаторы служат в отрядах, а не работают по задачам
+    prof != Profession::Likvidator
+}
+
+/// В каком помещении делается задача, если это известно.
+/// Зачистка - в своем помещении, стройка - там где стоит стационарка,
+/// герма сама себе помещение.
+fn task_room(
+    world: &World,
+    task: Entity,
+) -> Option<Entity> {
+    let entry = world.entry_ref(task).ok()?;
+    if let Ok(BelongsToRoom(room)) = entry.get_component::<BelongsToRoom>() {
+        return Some(*room)
+    }
+    let BelongsToStationary(stationary) = *entry
+        .get_component::<BelongsToStationary>()
+        .ok()?;
+    let stationary_entry = world.entry_ref(stationary).ok()?;
+    if stationary_entry.get_component::<Germ>().is_ok() {
+        return Some(stationary)
+    }
+    stationary_entry
+        .get_component::<BelongsToRoom>()
+        .ok()
+        .map(|BelongsToRoom(room)| *room)
+}
+
+/// Оборудование, которое может работать в этом ходу:
+/// (стационарка, тип, помещение, мощность на ход)
+fn working_stationaries(
+    world: &World,
+) -> Vec<(Entity, Stationary, Entity, BuildPower)> {
+    // В залитых слизью и бунтующих помещениях оборудование стоит,
+    // без коммуникаций - тоже
+    let mut contaminated_query = <Entity>::query()
+        .filter(component::<Contamination>() | component::<Riot>());
+    let contaminated: HashSet<Entity> = contaminated_query
+        .iter(world)
+        .cloned()
+        .collect();
+    let mut stationary_query = <(
+        Entity,
+        &Stationary,
+        &StationaryStatus,
+        &BelongsToRoom,
+    )>::query()
+        .filter(!component::<Unsupplied>());
+    stationary_query
+        .iter(world)
+        .filter(|(_, _, status, BelongsToRoom(room))|
+                **status == StationaryStatus::Ready
+                && !contaminated.contains(room))
+        .map(|(entity, stat, _, BelongsToRoom(room))|
+             (*entity, *stat, *room, stationary_build_power(*stat)))
+        .filter(|(_, _, _, bp)| *bp > BuildPower(0))
+        .collect()
+}
+
+/// Свободный станок, на котором можно делать эту работу.
+/// Сначала станок ровно нужного типа, потом старшего тира.
+/// Из равных - тот что в помещении задачи.
+fn pick_stationary(
+    stationaries: &[(Entity, Stationary, Entity, BuildPower)],
+    stationary: Stationary,
+    room: Option<Entity>,
+) -> Option<usize> {
+    stationaries
+        .iter()
+        .enumerate()
+        .filter(|(_, (_, stat, _, bp))|
+                stationary_serves(*stat, stationary)
+                && *bp > BuildPower(0))
+        .min_by_key(|(_, (_, stat, r, _))|
+                    (*stat != stationary, Some(*r) != room))
+        .map(|(i, _)| i)
 }
 
-/// Сформировать пул билдпавера
-fn calc_buildpower(
+/// Сколько труда комрад вкладывает в задачу своего или младшего тира
+fn effective_buildpower(
+    worker_tier: Tier,
+    task_tier: Tier,
+) -> BuildPower {
+    buildpower_downgrage_coef(
+        worker_tier,
+        task_tier,
+        tier2comrad_buildpower(worker_tier),
+    )
+}
+
+/// Расставить свободных комрадов по задачам.
+/// Задачи разбираются в порядке приоритета, на задачу ставится
+/// столько людей, сколько она и оборудование под нее могут занять.
+/// Сначала все расходятся по задачам своего тира, потом
+/// оставшиеся без дела старшие тиры добивают задачи младших.
+/// Из подходящих в первую очередь берутся те, кто приписан
+/// к помещению задачи.
+pub(crate) fn assign_tasks(
     world: &mut World,
-    resources: &mut Resources,
+    _resources: &mut Resources,
 ) {
-    let mut buildpower_pool = resources
-        .get_mut::<BuildPowerPool>()
-        .unwrap();
-    let mut people_query = <(
+    let stationaries = working_stationaries(world);
+    // Мощность под каждый тип работы. Старшее оборудование
+    // считается под все типы, которые тянет, так что это оценка сверху.
+    let mut stationary_capacity: HashMap<Stationary, BuildPower> =
+        HashMap::new();
+    for required in STATIONARIES.iter() {
+        for (_, stat, _, bp) in stationaries.iter() {
+            if stationary_serves(*stat, *required) {
+                *stationary_capacity
+                    .entry(*required)
+                    .or_insert(BuildPower(0)) += *bp;
+            }
+        }
+    }
+    // Работа без оборудования ограничена только руками
+    stationary_capacity.insert(Stationary::None, BuildPower(usize::MAX));
+
+    // Задачи на паузе не разбираются
+    let mut tasks_query = <(Entity, &TaskMeta, &TaskPriority)>::query()
+        .filter(!component::<Paused>());
+    let mut tasks: Vec<(Entity, TaskMeta, TaskPriority)> = tasks_query
+        .iter(world)
+        .filter(|(_, task, _)| task.bp > BuildPower(0))
+        .map(|(entity, task, priority)| (*entity, *task, *priority))
+        .collect();
+    tasks.sort_by_key(|(_, _, p1)| *p1);
+    let workable: HashSet<Entity> = tasks
+        .iter()
+        .filter(|(_, task, _)| stationary_capacity
+                .get(&task.stationary)
+                .is_some_and(|bp| *bp > BuildPower(0)))
+        .map(|(entity, _, _)| *entity)
+        .collect();
+
+    // Снять назначения с задач, которые закрыты, пропали,
+    // встали без оборудования или на паузе. Мутанты и раненые не работают.
+    let mut assigned_query = <(
+        Entity,
+        &AssignedTask,
+        Option<&Mutated>,
+        Option<&Injured>,
+    )>::query();
+    let released: Vec<Entity> = assigned_query
+        .iter(world)
+        .filter(|(_, AssignedTask(task), mutated, injured)|
+                mutated.is_some()
+                || injured.is_some()
+                || !workable.contains(task))
+        .map(|(comrad, _, _, _)| *comrad)
+        .collect();
+    for comrad in released.iter() {
+        if let Some(mut entry) = world.entry(*comrad) {
+            entry.remove_component::<AssignedTask>();
+        }
+    }
+
+    // Сколько рук уже стоит на задачах и на оборудовании
+    let mut planned: HashMap<Entity, BuildPower> = HashMap::new();
+    let mut load: HashMap<Stationary, BuildPower> = HashMap::new();
+    let task_metas: HashMap<Entity, TaskMeta> = tasks
+        .iter()
+        .map(|(entity, task, _)| (*entity, *task))
+        .collect();
+    let mut busy_query = <(&AssignedTask, &Tier)>::query();
+    for (AssignedTask(task), tier) in busy_query.iter(world) {
+        if let Some(meta) = task_metas.get(task) {
+            let human_bp = effective_buildpower(*tier, meta.tier);
+            *planned.entry(*task).or_insert(BuildPower(0)) += human_bp;
+            *load.entry(meta.stationary).or_insert(BuildPower(0)) += human_bp;
+        }
+    }
+
+    let mut idle_query = <(
+        Entity,
         &Profession,
         &Tier,
-    )>::query();
-    for (prof, tier) in people_query.iter(world) {
-        let human_bp = tier2comrad_buildpower(tier.clone());
-        let by_tier_hm = buildpower_pool
-            .entry(*prof)
-            .or_insert(HashMap::new());
+        &SciSpec,
+        Option<&BelongsToRoom>,
+    )>::query()
+        .filter(
+            !component::<AssignedTask>()
+            & !component::<Mutated>()
+            & !component::<Injured>()
+            & !component::<OnExpedition>()
+            & !component::<OnStrike>()
+        );
+    // Бездомные тоже работают, просто без "своего" помещения
+    let mut idle: Vec<(Entity, Profession, Tier, SciSpec, Option<Entity>)> = idle_query
+        .iter(world)
+        .filter(|(_, prof, _, _, _)| can_work(**prof))
+        .map(|(comrad, prof, tier, spec, home)|
+             (*comrad, *prof, *tier, *spec, home.map(|BelongsToRoom(room)| *room)))
+        .collect();
 
-        let bp = by_tier_hm
-            .entry(*tier)
-            .or_insert(BuildPower(0));
-        *bp += human_bp;
+    let mut assignments: Vec<(Entity, Entity)> = Vec::new();
+    for exact_tier in [true, false].iter() {
+        for (task, meta, _) in tasks.iter() {
+            if !workable.contains(task) {
+                continue
+            }
+            let room = task_room(world, *task);
+            let capacity = stationary_capacity[&meta.stationary];
+            // Научная работа идет только у ученых своего НИИ
+            let fits = |(_, prof, tier, spec, _): &(Entity, Profession, Tier, SciSpec, Option<Entity>)|
+                *prof == meta.prof
+                && (meta.sci_spec == SciSpec::None || *spec == meta.sci_spec)
+                && if *exact_tier {
+                    *tier == meta.tier
+                } else {
+                    effective_buildpower(*tier, meta.tier) > BuildPower(0)
+                };
+            loop {
+                let planned_bp = planned
+                    .get(task)
+                    .cloned()
+                    .unwrap_or(BuildPower(0));
+                let stationary_load = load
+                    .get(&meta.stationary)
+                    .cloned()
+                    .unwrap_or(BuildPower(0));
+                if planned_bp >= meta.bp || stationary_load >= capacity {
+                    break
+                }
+                let pick = idle
+                    .iter()
+                    .position(|worker| fits(worker) && worker.4.is_some() && worker.4 == room)
+                    .or_else(|| idle.iter().position(fits));
+                match pick {
+                    Some(i) => {
+                        let (comrad, _, tier, _, _) = idle.swap_remove(i);
+                        let human_bp = effective_buildpower(tier, meta.tier);
+                        *planned.entry(*task).or_insert(BuildPower(0)) += human_bp;
+                        *load.entry(meta.stationary).or_insert(BuildPower(0)) += human_bp;
+                        assignments.push((comrad, *task));
+                    },
+                    None => break,
+                }
+            }
+        }
+    }
+    for (comrad, task) in assignments.iter() {
+        if let Some(mut entry) = world.entry(*comrad) {
+            entry.add_component(AssignedTask(*task));
+        }
     }
 }
 
-/// Распределить все очки работы по заданиям
-/// TODO: Наивная реализация. T3 инженеры на T3
-/// станках тоже должны уметь делать T1 задания, причем
-/// более эффективно чем T1 работяги на T1 станках.
-/// Надо писать правила деградации.
-pub fn process_tasks(
+/// Отработать смену: каждый назначенный комрад вкладывает
+/// свой труд в задачу на свободном станке нужного типа.
+/// Старший тир на младшей задаче работает с коэффициентом
+/// из `buildpower_downgrage_coef`, настроение и сытость добавляют свои.
+/// Вложенный труд идет комраду в опыт, станки за смену изнашиваются.
+pub(crate) fn process_tasks(
     world: &mut World,
-    resources: &mut Resources,
+    _resources: &mut Resources,
 ) {
-    let mut buildpower_pool = resources
-        .get_mut::<BuildPowerPool>()
-        .unwrap();
-    let mut stationary_query = <(
-        &Stationary,
-        &StationaryStatus,
-    )>::query();
-    let mut stationaries:HashMap<Stationary, BuildPower> =
-        HashMap::new();
+    let mut stationaries = working_stationaries(world);
 
-    for (stat, status) in stationary_query.iter(world) {
-        if (*status) == StationaryStatus::Ready {
-            let bp = stationary_build_power(*stat);
-            let bp_for_update = stationaries
-                .entry(*stat)
-                .or_insert(BuildPower(0));
-            *bp_for_update += bp;
-        }
-    };
+    // Кто на какой задаче
+    let mut crews: HashMap<Entity, Vec<(Entity, Tier, Mood, Satiety)>> = HashMap::new();
+    let mut workers_query = <(Entity, &AssignedTask, &Tier, &Mood, &Satiety)>::query()
+        .filter(
+            !component::<Mutated>()
+            & !component::<Injured>()
+            & !component::<OnStrike>()
+        );
+    for (comrad, AssignedTask(task), tier, mood, satiety) in workers_query.iter(world) {
+        crews
+            .entry(*task)
+            .or_default()
+            .push((*comrad, *tier, *mood, *satiety));
+    }
+    let rooms: HashMap<Entity, Option<Entity>> = crews
+        .keys()
+        .map(|task| (*task, task_room(world, *task)))
+        .collect();
+
+    let mut worked: Vec<(Entity, BuildPower)> = Vec::new();
+    let mut used: HashSet<Entity> = HashSet::new();
     let mut tasks_query = <(
+        Entity,
         &TaskPriority,
         &mut TaskMeta,
-    )>::query();
+    )>::query()
+        .filter(!component::<Paused>());
     let mut tasks: Vec<(
+        &Entity,
         &TaskPriority,
         &mut TaskMeta,
     )> = tasks_query
         .iter_mut(world)
         .collect();
-    tasks.sort_by(|(p1, _), (p2, _)|(**p1).cmp(*p2));
-    for (_priority, task) in tasks.iter_mut() {
-        if let Some(stationary_bp) = stationaries.get_mut(&task.stationary) {
-            if let Some(by_tier) = buildpower_pool.get_mut(&task.prof) {
-                if let Some(human_bp) = by_tier.get_mut(&task.tier){
-                    let lesser_bp = min(
-                        human_bp.clone(),
-                        min(
-                            stationary_bp.clone(),
-                            task.bp.clone(),
-                        )
-                    );
-                    // Вот этот человек сделал
-                    *human_bp -= lesser_bp;
-                    // ...на на вот этом станке
-                    *stationary_bp -= lesser_bp;
-                    // ...вот столько работы
-                    task.bp -= lesser_bp;
+    tasks.sort_by(|(_, p1, _), (_, p2, _)|(**p1).cmp(*p2));
+    for (entity, _priority, task) in tasks.iter_mut() {
+        let crew = match crews.get(*entity) {
+            Some(crew) => crew,
+            None => continue,
+        };
+        let room = rooms.get(*entity).cloned().flatten();
+        for (comrad, worker_tier, mood, satiety) in crew.iter() {
+            if task.bp == BuildPower(0) {
+                break
+            }
+            let human_bp = satiety_buildpower(
+                *satiety,
+                mood_buildpower(
+                    *mood,
+                    effective_buildpower(*worker_tier, task.tier),
+                ),
+            );
+            let lesser_bp = if task.stationary == Stationary::None {
+                min(human_bp, task.bp)
+            } else {
+                match pick_stationary(&stationaries, task.stationary, room) {
+                    Some(i) => {
+                        let stationary_bp = &mut stationaries[i].3;
+                        let bp = min(
+                            human_bp,
+                            min(*stationary_bp, task.bp),
+                        );
+                        // ...на вот этом станке
+                        *stationary_bp -= bp;
+                        used.insert(stationaries[i].0);
+                        bp
+                    },
+                    // Все станки заняты, комрад простаивает
+                    None => continue,
                 }
+            };
+            // ...вот столько работы
+            task.bp -= lesser_bp;
+            // Вот этот человек сделал
+            worked.push((*comrad, lesser_bp));
+        }
+    }
+    for (comrad, bp) in worked.iter() {
+        if let Some(mut entry) = world.entry(*comrad) {
+            if let Ok(exp) = entry.get_component_mut::<Experience>() {
+                exp.0 += bp.0;
+            }
+        }
+    }
+    for stationary in used.iter() {
+        if let Some(mut entry) = world.entry(*stationary) {
+            if let Ok(durability) = entry.get_component_mut::<Durability>() {
+                durability.0 = durability.0.saturating_sub(WEAR_PER_SHIFT);
             }
         }
     }
 }
 
 /// Убрать выполненные таски
-pub fn clean_up_completed_tasks(
+pub(crate) fn clean_up_completed_tasks(
     world: &mut World,
     _resources: &mut Resources,
 ) {
     let mut to_delete:HashSet<Entity> = HashSet::new();
-    let mut query = <(&Entity, &TaskMeta)>::query();
+    let mut query = <(Entity, &TaskMeta)>::query();
     for (entity, task) in query.iter(world) {
         if task.bp == BuildPower (0) {
             // Весь требуемый билдпавер влит в эту задачу
             // Задача завершена.
-            to_delete.insert(entity.clone());
+            to_delete.insert(*entity);
         };
     };
     for entity in to_delete.iter () {
@@ -134,7 +425,7 @@ pub fn clean_up_completed_tasks(
 /// Глянуть если есть завершенные задания по строительству
 /// стационарных объектов
 /// Если есть - ввести в эксплуатацию.
-pub fn setup_completed_stationaries(
+pub(crate) fn setup_completed_stationaries(
     world: &mut World,
     _resources: &mut Resources,
 ) {
@@ -147,7 +438,8 @@ pub fn setup_completed_stationaries(
     for BelongsToStationary(entity) in under_construction_q.iter (world) {
         under_construction.insert(*entity);
     };
-    let mut stats_query = <(&Entity, &mut StationaryStatus)>::query();
+    let mut completed: Vec<Entity> = Vec::new();
+    let mut stats_query = <(Entity, &mut StationaryStatus)>::query();
     // стационарки которые строятся и не введены в эксплуатацию
     for (entity, status) in stats_query
         .iter_mut(world)
@@ -158,72 +450,145 @@ pub fn setup_completed_stationaries(
         // статус стационарного объекта - конструируется.
         // но по нему нет активных задач.
         // И на самом деле это означает что конструкция завершена.
-        if let Some(_) = under_construction.get(entity) {
+        if !under_construction.contains(entity) {
             *status = StationaryStatus::Ready;
+            completed.push(*entity);
+        }
+    }
+    // Стеллажи и чаны обзаводятся местами хранения,
+    // новое оборудование - запасом прочности
+    for entity in completed.iter() {
+        spawn_stationary_containers(world, *entity);
+        if let Some(mut entry) = world.entry(*entity) {
+            if entry.get_component::<Stationary>().is_ok() {
+                entry.add_component(Durability(MAX_DURABILITY));
+            }
         }
     }
 }
 
-/// Голод
-pub fn hunger_tick(
-    world: &mut World,
-    _resources: &mut Resources,
-) {
-    let mut died_by_hunger: Vec<Entity> = Vec::new();
-    let mut query = <(
-        &Entity,
-        &mut Satiety,
-        &mut Mood,
-    )>::query();
-    for (entity, sat, mood) in query.iter_mut(world) {
-        sat.0 -= 10;
-        if sat.0 < 10 {
-            died_by_hunger.push(*entity);
-        }
-        // ниже ста - голод - минус настроение
-        if sat.0 < 100 {
-            mood.0 -= 1;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colony::init_resources;
+
+    fn job(
+        world: &mut World,
+        owner: Entity,
+        tier: Tier,
+        bp: usize,
+        stationary: Stationary,
+    ) -> Entity {
+        push_task(world, BelongsToStationary(owner), TaskMeta {
+            prof: Profession::Worker,
+            tier,
+            bp: BuildPower(bp),
+            stationary,
+            sci_spec: SciSpec::None,
+        }, TaskPriority(0))
+    }
+
+    fn bp_left(world: &World, task: Entity) -> BuildPower {
+        world.entry_ref(task).unwrap().get_component::<TaskMeta>().unwrap().bp
+    }
+
+    fn worker(world: &mut World, tier: Tier, room: Entity) -> Entity {
+        spawn_comrad(world, Profession::Worker, tier, MilitaryDep::None, SciSpec::None, room).unwrap()
+    }
+
+    #[test]
+    fn test_each_comrad_on_own_task() {
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        let room = spawn_ready_germ(&mut world, Tier::T1, AreaType::Industrial).unwrap();
+        let first = job(&mut world, room, Tier::T1, 10, Stationary::None);
+        let second = job(&mut world, room, Tier::T1, 10, Stationary::None);
+        let ivan = worker(&mut world, Tier::T1, room);
+        let petr = worker(&mut world, Tier::T1, room);
+        let idler = worker(&mut world, Tier::T1, room);
+        assign_tasks(&mut world, &mut resources);
+        let task_of = |comrad: Entity| world
+            .entry_ref(comrad)
+            .unwrap()
+            .get_component::<AssignedTask>()
+            .ok()
+            .map(|AssignedTask(task)| *task);
+        // Двое разошлись по разным задачам, третьему работы не хватило
+        let crew = [ivan, petr, idler];
+        let jobs: Vec<Option<Entity>> = crew.iter().map(|c| task_of(*c)).collect();
+        assert_eq!(jobs.iter().filter(|job| job.is_none()).count(), 1);
+        let assigned: HashSet<Entity> = jobs.iter().flatten().cloned().collect();
+        assert_eq!(assigned, [first, second].iter().cloned().collect());
+        assert_eq!(busy_comrads(&mut world), 2);
+        assert_eq!(idle_comrads(&mut world), 1);
+
+        process_tasks(&mut world, &mut resources);
+        assert_eq!(bp_left(&world, first), BuildPower(0));
+        assert_eq!(bp_left(&world, second), BuildPower(0));
+        // Опыт получили только работавшие
+        let experience: Vec<Experience> = crew
+            .iter()
+            .map(|c| *world.entry_ref(*c).unwrap().get_component::<Experience>().unwrap())
+            .collect();
+        for (job, exp) in jobs.iter().zip(experience.iter()) {
+            let expected = if job.is_some() { 10 } else { 0 };
+            assert_eq!(*exp, Experience(expected));
         }
     }
-    for e in died_by_hunger.iter() {
-        world.remove(*e);
+
+    #[test]
+    fn test_senior_does_junior_work() {
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        let room = spawn_ready_germ(&mut world, Tier::T1, AreaType::Industrial).unwrap();
+        let task = job(&mut world, room, Tier::T1, 100, Stationary::None);
+        worker(&mut world, Tier::T2, room);
+        assign_tasks(&mut world, &mut resources);
+        process_tasks(&mut world, &mut resources);
+        // T2 на T1 работе - вдвое от своих 20
+        assert_eq!(bp_left(&world, task), BuildPower(60));
     }
-}
 
-/// Люди едят концентрат
-pub fn consume_concentrat(
-    world: &mut World,
-    _resources: &mut Resources,
-) {
-    // сколько есть на складе
-    let mut t1_conc_amount = how_much_we_have(
-        world,
-        Resource::ConcentratT1,
-    );
-    // Сколько выдано
-    let mut t1_conc_writeroff = 0;
-    // имеет настроение = человек.
-    // да, знаю, зашибись признак.
-    let mut query = <(
-        &mut Mood,
-        &mut Satiety,
-    )>::query();
+    #[test]
+    fn test_senior_stationary_serves_junior_work() {
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        let room = spawn_ready_germ(&mut world, Tier::T1, AreaType::Industrial).unwrap();
+        spawn_ready_stationary(&mut world, Stationary::BenchToolT2, room);
+        let task = job(&mut world, room, Tier::T1, 100, Stationary::BenchToolT1);
+        worker(&mut world, Tier::T1, room);
+        assign_tasks(&mut world, &mut resources);
+        process_tasks(&mut world, &mut resources);
+        assert_eq!(bp_left(&world, task), BuildPower(90));
+    }
+
+    #[test]
+    fn test_overeaten_work_slower() {
+        let mut world = World::default();
+        let mut resources = Resources::default();
+        let room = spawn_ready_germ(&mut world, Tier::T1, AreaType::Industrial).unwrap();
+        let task = job(&mut world, room, Tier::T2, 100, Stationary::None);
+        let glutton = worker(&mut world, Tier::T2, room);
+        if let Some(mut entry) = world.entry(glutton) {
+            entry.add_component(Satiety(OVEREATING_SATIETY + 1));
+        }
+        assign_tasks(&mut world, &mut resources);
+        process_tasks(&mut world, &mut resources);
+        assert_eq!(bp_left(&world, task), BuildPower(85));
+    }
 
-    for (mood, sat) in query.iter_mut(world){
-        if t1_conc_amount.0 <= 0 {
-            // Не дали пожрать. Настроение
-            // от такого ухудшается.
-            mood.0 -= 1;
-        } else {
-            t1_conc_amount.0 -= 1;
-            t1_conc_writeroff += 1;
-            mood.0 += 1;
-            sat.0 += 10;
+    #[test]
+    fn test_hunger_bottoms_out_mood() {
+        let mut world = World::default();
+        let mut resources = init_resources(0);
+        let room = world.push(());
+        let comrad = spawn_comrad(&mut world, Profession::Worker, Tier::T1, MilitaryDep::None, SciSpec::None, room).unwrap();
+        // Голодная смерть наступает позже, чем настроение падает до нуля
+        for _ in 0..6 {
+            hunger_tick(&mut world, &mut resources);
+            consume_concentrat(&mut world, &mut resources);
         }
+        let mood = *world.entry_ref(comrad).unwrap().get_component::<Mood>().unwrap();
+        assert_eq!(mood, Mood(0));
     }
-    writeoff(
-        world,
-        Resource::ConcentratT1,
-        RealUnits(t1_conc_writeroff),
-    );
 }
@@ -0,0 +1,272 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use legion::*;
+use serde::{Deserialize, Serialize};
+
+use crate::core::*;
+use crate::area::*;
+use crate::people::*;
+use crate::production::*;
+use crate::expedition::OnExpedition;
+use crate::military::*;
+
+/// При таком настроении и ниже комрад бросает работу
+const STRIKE_MOOD: u8 = 2;
+
+/// С такого настроения забастовщик возвращается к работе
+const BACK_TO_WORK_MOOD: u8 = 4;
+
+/// При таком настроении и ниже комрад работает спустя рукава
+const LOW_MOOD: u8 = 4;
+
+/// С такого настроения комрад работает с огоньком
+const ENTHUSIASM_MOOD: u8 = 8;
+
+/// Сколько забастовщиков в одном помещении поднимают бунт
+const RIOT_CROWD: usize = 3;
+
+/// Сколько силы отрядов ОБЧУ/ОГБ держит одного бунтовщика
+const STRENGTH_PER_RIOTER: usize = 3;
+
+/// Комрад бастует. Не работает.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OnStrike ();
+
+/// В помещении бунт. Оборудование стоит,
+/// люди в помещении теряют настроение.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Riot {
+    pub rioters: usize,
+}
+
+/// Что натворило настроение за ход
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct UnrestReport {
+    pub strikers: usize,
+    pub deserted: usize,
+    pub riots: usize,
+}
+
+/// Сколько труда комрад выдает при таком настроении.
+/// Несчастный работает вполсилы, довольный - на четверть лучше.
+pub fn mood_buildpower(
+    mood: Mood,
+    bp: BuildPower,
+) -> BuildPower {
+    if mood.0 <= LOW_MOOD {
+        BuildPower(bp.0 / 2)
+    } else if mood.0 >= ENTHUSIASM_MOOD {
+        BuildPower(bp.0 * 5 / 4)
+    } else {
+        bp
+    }
+}
+
+/// Отряды, которые разгоняют бунты
+fn is_security(dep: MilitaryDep) -> bool {
+    dep == MilitaryDep::OBCU || dep == MilitaryDep::OGB
+}
+
+/// Последствия настроения: забастовки, дезертирство, бунты.
+/// Свободные отряды ОБЧУ и ОГБ сами выходят на бунтующие помещения
+/// и держат их пока там есть толпа.
+pub fn unrest_tick(
+    world: &mut World,
+    resources: &mut Resources,
+) -> UnrestReport {
+    let mut report = UnrestReport::default();
+    let mut dice = resources
+        .get_mut::<Dice>()
+        .unwrap();
+
+    // Забастовки
+    let mut people_q = <(Entity, &Mood, Option<&OnStrike>)>::query()
+        .filter(component::<Profession>() & !component::<OnExpedition>());
+    let mut strike: Vec<Entity> = Vec::new();
+    let mut back_to_work: Vec<Entity> = Vec::new();
+    let mut deserted: Vec<Entity> = Vec::new();
+    for (entity, mood, on_strike) in people_q.iter(world) {
+        if mood.0 == 0 && d(&mut dice, 1, 6) == 1 {
+            // Совсем невмоготу - ушел из колонии
+            deserted.push(*entity);
+        } else if mood.0 <= STRIKE_MOOD && on_strike.is_none() {
+            strike.push(*entity);
+        } else if mood.0 >= BACK_TO_WORK_MOOD && on_strike.is_some() {
+            back_to_work.push(*entity);
+        }
+    }
+    for entity in strike.iter() {
+        if let Some(mut entry) = world.entry(*entity) {
+            entry.remove_component::<AssignedTask>();
+            entry.add_component(OnStrike());
+        }
+    }
+    for entity in back_to_work.iter() {
+        if let Some(mut entry) = world.entry(*entity) {
+            entry.remove_component::<OnStrike>();
+        }
+    }
+    for entity in deserted.iter() {
+        world.remove(*entity);
+    }
+    report.deserted = deserted.len();
+
+    // Толпы забастовщиков по помещениям
+    let mut crowds: HashMap<Entity, usize> = HashMap::new();
+    let mut strikers_q = <&BelongsToRoom>::query()
+        .filter(component::<OnStrike>());
+    for BelongsToRoom(room) in strikers_q.iter(world) {
+        *crowds.entry(*room).or_insert(0) += 1;
+    }
+    report.strikers = crowds.values().sum();
+    crowds.retain(|_, crowd| *crowd >= RIOT_CROWD);
+
+    // Свободные отряды безопасности выходят на толпы,
+    // отработавшие возвращаются в казармы
+    let mut squads_q = <(Entity, &Squad, Option<&DispatchedTo>)>::query();
+    let squads: Vec<(Entity, Option<Entity>)> = squads_q
+        .iter(world)
+        .filter(|(_, squad, _)| is_security(squad.dep))
+        .map(|(e, _, dispatched)| (*e, dispatched.map(|DispatchedTo(room)| *room)))
+        .collect();
+    // Помещения берем в порядке запроса, а не HashMap,
+    // чтобы при одном сиде отряды выходили на те же толпы
+    let mut rooms_q = <(Entity, &AreaType)>::query();
+    let rooms: Vec<Entity> = rooms_q
+        .iter(world)
+        .map(|(e, _)| *e)
+        .collect();
+    let mut uncovered: Vec<Entity> = rooms
+        .iter()
+        .filter(|room| crowds.contains_key(*room))
+        .filter(|room| !squads.iter().any(|(_, at)| at.as_ref() == Some(*room)))
+        .cloned()
+        .collect();
+    // Первой закрывают самую большую толпу
+    uncovered.sort_by_key(|room| crowds[room]);
+    for (squad, at) in squads.iter() {
+        match at {
+            Some(room) if !crowds.contains_key(room) => {
+                if let Some(mut entry) = world.entry(*squad) {
+                    entry.remove_component::<DispatchedTo>();
+                }
+            },
+            Some(_) => (),
+            None => {
+                if let Some(room) = uncovered.pop() {
+                    if let Some(mut entry) = world.entry(*squad) {
+                        entry.add_component(DispatchedTo(room));
+                    }
+                }
+            },
+        }
+    }
+
+    // Бунт там, где толпу некому держать
+    let strength = squad_strength(world);
+    let mut security: HashMap<Entity, usize> = HashMap::new();
+    let mut positions_q = <(&Squad, Entity, &DispatchedTo)>::query();
+    for (squad, entity, DispatchedTo(room)) in positions_q.iter(world) {
+        if is_security(squad.dep) {
+            *security.entry(*room).or_insert(0) += strength
+                .get(entity)
+                .cloned()
+                .unwrap_or(0);
+        }
+    }
+    let mut rioting: HashSet<Entity> = HashSet::new();
+    for room in rooms.iter() {
+        let rioters = crowds.get(room).cloned().unwrap_or(0);
+        let held = security.get(room).cloned().unwrap_or(0)
+            >= rioters * STRENGTH_PER_RIOTER;
+        if let Some(mut entry) = world.entry(*room) {
+            if rioters > 0 && !held {
+                entry.add_component(Riot { rioters });
+                rioting.insert(*room);
+            } else {
+                entry.remove_component::<Riot>();
+            }
+        }
+    }
+
+    // В бунтующих помещениях всем плохо
+    let mut residents_q = <(&BelongsToRoom, &mut Mood)>::query();
+    for (BelongsToRoom(room), mood) in residents_q.iter_mut(world) {
+        if rioting.contains(room) {
+            mood.lower(1);
+        }
+    }
+    report.riots = rioting.len();
+    report
+}
+
+/// Сколько сейчас бастует
+pub fn strikers(
+    world: &mut World,
+) -> usize {
+    let mut query = <&OnStrike>::query();
+    query.iter(world).count()
+}
+
+/// Сколько помещений бунтует
+pub fn riots(
+    world: &mut World,
+) -> usize {
+    let mut query = <&Riot>::query();
+    query.iter(world).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colony::init_resources;
+
+    fn crowd(world: &mut World, room: Entity) {
+        for _ in 0..RIOT_CROWD {
+            let comrad = spawn_comrad(world, Profession::Worker, Tier::T1, MilitaryDep::None, SciSpec::None, room).unwrap();
+            if let Some(mut entry) = world.entry(comrad) {
+                entry.add_component(Mood(STRIKE_MOOD));
+            }
+        }
+    }
+
+    #[test]
+    fn test_mood_changes_work() {
+        assert_eq!(mood_buildpower(Mood(LOW_MOOD), BuildPower(20)), BuildPower(10));
+        assert_eq!(mood_buildpower(Mood(5), BuildPower(20)), BuildPower(20));
+        assert_eq!(mood_buildpower(Mood(ENTHUSIASM_MOOD), BuildPower(20)), BuildPower(25));
+    }
+
+    #[test]
+    fn test_strikers_riot() {
+        let mut world = World::default();
+        let mut resources = init_resources(0);
+        let cell = spawn_ready_germ(&mut world, Tier::T1, AreaType::Living).unwrap();
+        crowd(&mut world, cell);
+        let report = unrest_tick(&mut world, &mut resources);
+        assert_eq!(report.strikers, RIOT_CROWD);
+        assert_eq!(report.riots, 1);
+        assert!(world.entry_ref(cell).unwrap().get_component::<Riot>().is_ok());
+    }
+
+    #[test]
+    fn test_security_holds_crowd() {
+        let mut world = World::default();
+        let mut resources = init_resources(0);
+        let cell = spawn_ready_germ(&mut world, Tier::T1, AreaType::Living).unwrap();
+        let barracks = spawn_ready_germ(&mut world, Tier::T2, AreaType::Military).unwrap();
+        let squad = form_squad(&mut world, MilitaryDep::OBCU, barracks).unwrap();
+        for _ in 0..2 {
+            let soldier = spawn_comrad(&mut world, Profession::Likvidator, Tier::T3, MilitaryDep::OBCU, SciSpec::None, barracks).unwrap();
+            reinforce_squad(&mut world, squad, soldier, SquadRole::Granit).unwrap();
+        }
+        crowd(&mut world, cell);
+        let report = unrest_tick(&mut world, &mut resources);
+        assert_eq!(report.riots, 0);
+        assert_eq!(
+            world.entry_ref(squad).unwrap().get_component::<DispatchedTo>().ok(),
+            Some(&DispatchedTo(cell)),
+        );
+    }
+}
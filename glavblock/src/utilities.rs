@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use legion::*;
+use serde::{Deserialize, Serialize};
+
+use crate::core::*;
+use crate::area::*;
+use crate::production::*;
+
+/// Коммуникации помещения
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Utility {
+    Power, // электричество
+    Water, // водопровод
+    Air, // вентиляция
+}
+
+/// К каким узлам подключено помещение
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UtilityConnections(pub HashMap<Utility, Entity>);
+
+/// Стационарке не хватает коммуникаций. Стоит.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Unsupplied ();
+
+/// Что за узел эта стационарка и сколько помещений она тянет
+pub fn utility_node(
+    stationary: Stationary,
+) -> Option<(Utility, usize)> {
+    match stationary {
+        Stationary::Generator => Some((Utility::Power, 6)),
+        Stationary::WaterPump => Some((Utility::Water, 4)),
+        Stationary::AirPump => Some((Utility::Air, 4)),
+        _ => None,
+    }
+}
+
+/// Что нужно стационарке в помещении, чтобы работать.
+/// Ручной верстак и стеллаж работают и так.
+pub fn stationary_utilities(
+    stationary: Stationary,
+) -> Vec<Utility> {
+    match stationary {
+        Stationary::BenchToolT2 => vec![Utility::Power],
+        Stationary::BenchToolT3 => vec![Utility::Power, Utility::Air],
+        Stationary::FormatFurnace => vec![Utility::Power, Utility::Air],
+        Stationary::LabT1 => vec![Utility::Power],
+        Stationary::LabT2 => vec![Utility::Power, Utility::Water],
+        Stationary::LabT3 => vec![Utility::Power, Utility::Water, Utility::Air],
+        Stationary::Barrel => vec![Utility::Water],
+        Stationary::NeuroTerminal => vec![Utility::Power],
+        Stationary::WaterPump => vec![Utility::Power],
+        Stationary::AirPump => vec![Utility::Power],
+        _ => Vec::new(),
+    }
+}
+
+/// Сколько помещений подключено к каждому узлу
+pub fn node_load(
+    world: &World,
+) -> HashMap<Entity, usize> {
+    let mut result: HashMap<Entity, usize> = HashMap::new();
+    let mut query = <&UtilityConnections>::query();
+    for UtilityConnections(connections) in query.iter(world) {
+        for node in connections.values() {
+            *result.entry(*node).or_insert(0) += 1;
+        }
+    }
+    result
+}
+
+/// Подключить помещение к узлу. Узел заменяет прежнее
+/// подключение того же вида.
+pub fn connect_room(
+    world: &mut World,
+    room: Entity,
+    node: Entity,
+) -> Result<(), SamosborError> {
+    get_room_free_space(world, room)?;
+    let (utility, capacity) = world
+        .entry_ref(node)
+        .map_err(|_| SamosborError::NoSuchEntity(node))?
+        .get_component::<Stationary>()
+        .ok()
+        .and_then(|stationary| utility_node(*stationary))
+        .ok_or(SamosborError::NotAUtilityNode(node))?;
+    // К недостроенному или разбираемому узлу не подключаются
+    if world
+        .entry_ref(node)
+        .ok()
+        .and_then(|entry| entry.get_component::<StationaryStatus>().ok().cloned())
+        != Some(StationaryStatus::Ready)
+    {
+        return Err(SamosborError::StationaryBusy(node))
+    }
+    let mut connections = world
+        .entry_ref(room)
+        .map_err(|_| SamosborError::NoSuchEntity(room))?
+        .get_component::<UtilityConnections>()
+        .cloned()
+        .unwrap_or_default();
+    if connections.0.get(&utility) == Some(&node) {
+        return Ok(())
+    }
+    let load = node_load(world).get(&node).cloned().unwrap_or(0);
+    if load >= capacity {
+        return Err(SamosborError::NodeOverloaded(node))
+    }
+    connections.0.insert(utility, node);
+    if let Some(mut entry) = world.entry(room) {
+        entry.add_component(connections);
+    }
+    Ok(())
+}
+
+/// Отключить помещение от узла
+pub fn disconnect_room(
+    world: &mut World,
+    room: Entity,
+    utility: Utility,
+) -> Result<(), SamosborError> {
+    get_room_free_space(world, room)?;
+    if let Some(mut entry) = world.entry(room) {
+        if let Ok(connections) = entry.get_component_mut::<UtilityConnections>() {
+            connections.0.remove(&utility);
+        }
+    }
+    Ok(())
+}
+
+/// Отключить от узла все помещения. Узел убрали или перенесли,
+/// подключать заново - вручную.
+pub(crate) fn disconnect_node(
+    world: &mut World,
+    node: Entity,
+) {
+    let mut connections_q = <&mut UtilityConnections>::query();
+    for UtilityConnections(links) in connections_q.iter_mut(world) {
+        links.retain(|_, linked| *linked != node);
+    }
+}
+
+/// Что реально подается в каждое помещение.
+/// Узел работает, если он готов и у его помещения есть
+/// все что узлу нужно: насосы без электричества стоят.
+pub fn room_supply(
+    world: &World,
+) -> HashMap<Entity, HashSet<Utility>> {
+    let mut nodes_q = <(Entity, &Stationary, &StationaryStatus, &BelongsToRoom)>::query();
+    let nodes: Vec<(Entity, Stationary, Entity)> = nodes_q
+        .iter(world)
+        .filter(|(_, stationary, status, _)|
+                utility_node(**stationary).is_some()
+                && **status == StationaryStatus::Ready)
+        .map(|(e, stationary, _, BelongsToRoom(room))| (*e, *stationary, *room))
+        .collect();
+    let mut connections_q = <(Entity, &UtilityConnections)>::query();
+    let connections: Vec<(Entity, UtilityConnections)> = connections_q
+        .iter(world)
+        .map(|(room, connections)| (*room, connections.clone()))
+        .collect();
+
+    // Узлы запитывают друг друга, так что считаем до тех пор,
+    // пока не перестанут включаться новые
+    let mut working: HashSet<Entity> = HashSet::new();
+    let mut supply: HashMap<Entity, HashSet<Utility>> = HashMap::new();
+    loop {
+        supply.clear();
+        for (room, UtilityConnections(links)) in connections.iter() {
+            let supplied: HashSet<Utility> = links
+                .iter()
+                .filter(|(_, node)| working.contains(*node))
+                .map(|(utility, _)| *utility)
+                .collect();
+            supply.insert(*room, supplied);
+        }
+        let before = working.len();
+        for (node, stationary, room) in nodes.iter() {
+            let supplied = supply.get(room);
+            let runs = stationary_utilities(*stationary)
+                .iter()
+                .all(|utility| supplied.map(|s| s.contains(utility)).unwrap_or(false));
+            if runs {
+                working.insert(*node);
+            }
+        }
+        if working.len() == before {
+            break
+        }
+    }
+    supply
+}
+
+/// Пометить готовые стационарки, которым не хватает коммуникаций
+pub fn utilities_tick(
+    world: &mut World,
+    _resources: &mut Resources,
+) {
+    let supply = room_supply(world);
+    let mut stationary_q = <(
+        Entity,
+        &Stationary,
+        &StationaryStatus,
+        &BelongsToRoom,
+        Option<&Unsupplied>,
+    )>::query();
+    let changes: Vec<(Entity, bool)> = stationary_q
+        .iter(world)
+        .filter_map(|(entity, stationary, status, BelongsToRoom(room), unsupplied)| {
+            let supplied = supply.get(room);
+            let runs = *status != StationaryStatus::Ready
+                || stationary_utilities(*stationary)
+                .iter()
+                .all(|utility| supplied.map(|s| s.contains(utility)).unwrap_or(false));
+            if runs == unsupplied.is_some() {
+                Some((*entity, runs))
+            } else {
+                None
+            }
+        })
+        .collect();
+    for (entity, runs) in changes.iter() {
+        if let Some(mut entry) = world.entry(*entity) {
+            if *runs {
+                entry.remove_component::<Unsupplied>();
+            } else {
+                entry.add_component(Unsupplied());
+            }
+        }
+    }
+}
+
+/// Сколько стационарок стоит без коммуникаций
+pub fn unsupplied_stationaries(
+    world: &mut World,
+) -> usize {
+    let mut query = <&Unsupplied>::query();
+    query.iter(world).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_node_must_be_ready() {
+        let mut world = World::default();
+        let room = spawn_ready_germ(&mut world, Tier::T1, AreaType::Industrial).unwrap();
+        let node = world.push((
+            Stationary::Generator,
+            StationaryStatus::Constructing,
+            BelongsToRoom(room),
+        ));
+        assert_eq!(
+            connect_room(&mut world, room, node),
+            Err(SamosborError::StationaryBusy(node)),
+        );
+    }
+
+    #[test]
+    fn test_dismantled_node_disconnected() {
+        let mut world = World::default();
+        let room = spawn_ready_germ(&mut world, Tier::T2, AreaType::Industrial).unwrap();
+        let node = spawn_ready_stationary(&mut world, Stationary::Generator, room);
+        connect_room(&mut world, room, node).unwrap();
+        assert_eq!(node_load(&world)[&node], 1);
+        dismantle_stationary(&mut world, node).unwrap();
+        assert!(node_load(&world).is_empty());
+        let connections = world
+            .entry_ref(room)
+            .unwrap()
+            .get_component::<UtilityConnections>()
+            .unwrap()
+            .clone();
+        assert_eq!(connections, UtilityConnections::default());
+    }
+}
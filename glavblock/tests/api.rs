@@ -0,0 +1,51 @@
+use legion::*;
+
+use glavblock::*;
+
+/// Стартовая колония с костями на нулевом сиде
+fn colony() -> (World, Resources) {
+    let mut world = World::default();
+    let mut resources = init_resources(0);
+    init_colony(&mut world, &mut resources).unwrap();
+    (world, resources)
+}
+
+#[test]
+fn test_build_stationary_through_public_api() {
+    let (mut world, mut resources) = colony();
+    let scrap = how_much_we_have(&mut world, Resource::ScrapT1);
+    let workshop = spawn_ready_germ(&mut world, Tier::T1, AreaType::Industrial).unwrap();
+    let bench = start_build_task(
+        &mut world,
+        Stationary::BenchToolT1,
+        workshop,
+        TaskPriority(0),
+    ).unwrap();
+    assert_eq!(
+        how_much_we_have(&mut world, Resource::ScrapT1),
+        RealUnits(scrap.0 - 1),
+    );
+    for _ in 0..3 {
+        turn(&mut world, &mut resources);
+    }
+    let status = *world
+        .entry_ref(bench)
+        .unwrap()
+        .get_component::<StationaryStatus>()
+        .unwrap();
+    assert_eq!(status, StationaryStatus::Ready);
+}
+
+#[test]
+fn test_put_resource_shows_up_in_stock() {
+    let (mut world, _) = colony();
+    // Стартовый склад забит под завязку, нужен еще один
+    spawn_ready_germ(&mut world, Tier::T1, AreaType::Party).unwrap();
+    let before = how_much_we_have(&mut world, Resource::ComponentT1);
+    put_resource(&mut world, Resource::ComponentT1, RealUnits(3)).unwrap();
+    assert_eq!(
+        how_much_we_have(&mut world, Resource::ComponentT1),
+        RealUnits(before.0 + 3),
+    );
+    assert_eq!(what_we_have(&mut world)[&Resource::ComponentT1], RealUnits(before.0 + 3));
+}
@@ -0,0 +1,32 @@
+use std::process::Command;
+
+/// Дайджест вывода `glavblock-headless 60 7`.
+/// Поменялся баланс или отчет - прогнать и вписать новый.
+const GOLDEN_DIGEST: u64 = 4346962959266908052;
+
+/// Прогнать колонию в отдельном процессе.
+/// У каждого процесса свои ключи HashMap, так что
+/// зависимость от порядка в хэш-таблицах вылезет здесь.
+fn headless(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_glavblock-headless"))
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// FNV-1a. Свой, чтобы дайджест не зависел от версии std.
+fn digest(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[test]
+fn test_same_seed_same_digest_across_processes() {
+    let first = headless(&["60", "7"]);
+    let second = headless(&["60", "7"]);
+    assert_eq!(first, second);
+    assert_eq!(digest(&first), GOLDEN_DIGEST);
+}